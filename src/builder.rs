@@ -0,0 +1,189 @@
+use regex::Regex;
+
+use crate::chain::{STATE_SIZE, Smoothing};
+use crate::error::MarukovError;
+use crate::splitter::SentenceSplitter;
+use crate::text::{BEGIN, END, Text};
+use crate::tokenizer::Tokenizer;
+use crate::vocab::{Normalization, Vocab};
+
+/// TextBuilder configures how a `Text` is trained before building it.
+#[derive(Debug, Clone)]
+pub struct TextBuilder {
+    pub(crate) order: usize,
+    pub(crate) reject: Option<Regex>,
+    pub(crate) normalization: Normalization,
+    pub(crate) splitter: SentenceSplitter,
+    pub(crate) split_punctuation: bool,
+    pub(crate) unidecode_input: bool,
+    pub(crate) skip_short_sentences: bool,
+    pub(crate) sentinels: (String, String),
+    pub(crate) min_begin_weight: i32,
+    pub(crate) smoothing: Smoothing,
+    pub(crate) strip_numbers: bool,
+    pub(crate) number_placeholder: Option<String>,
+}
+
+impl Default for TextBuilder {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl TextBuilder {
+    /// Creates a builder with the same settings as `Text::new`.
+    pub fn new() -> Self {
+        Self {
+            order: STATE_SIZE,
+            reject: Some(Text::default_reject()),
+            normalization: Normalization::None,
+            splitter: SentenceSplitter::default(),
+            split_punctuation: false,
+            unidecode_input: true,
+            skip_short_sentences: false,
+            sentinels: (BEGIN.to_string(), END.to_string()),
+            min_begin_weight: 0,
+            smoothing: Smoothing::None,
+            strip_numbers: false,
+            number_placeholder: None,
+        }
+    }
+
+    /// Sets the number of words in a state, see `Text::with_order`.
+    pub fn order(mut self, order: usize) -> Self {
+        self.order = order;
+        self
+    }
+
+    /// Sets the pattern for rejecting input lines, see `Text::with_reject`.
+    pub fn reject(mut self, reject: Option<Regex>) -> Self {
+        self.reject = reject;
+        self
+    }
+
+    /// Sets the pattern for rejecting input lines from a regular expression.
+    /// # Returns
+    /// The builder, or `MarukovError::InvalidPattern` if the pattern doesn't compile.
+    pub fn reject_pattern(self, pattern: &str) -> Result<Self, MarukovError> {
+        Ok(self.reject(Some(Regex::new(pattern)?)))
+    }
+
+    /// Sets whether words are looked up ignoring case, see `Vocab::new_case_insensitive`.
+    /// Shorthand for `normalization(Normalization::Lowercase)`.
+    pub fn case_insensitive(mut self, case_insensitive: bool) -> Self {
+        self.normalization = if case_insensitive {
+            Normalization::Lowercase
+        } else {
+            Normalization::None
+        };
+        self
+    }
+
+    /// Sets how words are normalized before looking them up, see `Normalization`.
+    /// The output always shows words in the form they were first seen in.
+    pub fn normalization(mut self, normalization: Normalization) -> Self {
+        self.normalization = normalization;
+        self
+    }
+
+    /// Sets how the data is split into sentences, see `SentenceSplitter`.
+    pub fn splitter(mut self, splitter: SentenceSplitter) -> Self {
+        self.splitter = splitter;
+        self
+    }
+
+    /// Sets whether trailing punctuation is kept as separate tokens, see `Text::with_punctuation`.
+    pub fn split_punctuation(mut self, split_punctuation: bool) -> Self {
+        self.split_punctuation = split_punctuation;
+        self
+    }
+
+    /// Sets whether input lines are transliterated to ASCII before matching the reject pattern.
+    /// On by default, turn it off for non-Latin scripts, which transliteration would mangle.
+    /// Only the reject check is affected, words are always stored as they appear.
+    pub fn unidecode_input(mut self, unidecode_input: bool) -> Self {
+        self.unidecode_input = unidecode_input;
+        self
+    }
+
+    /// Sets whether sentences with fewer words than the order are left out of the model.
+    ///
+    /// By default they are kept: a one-word line still adds a path from the begin state through
+    /// the word to the end, so it can be generated. Note that such an output is always a verbatim
+    /// copy of its line, which the overlap check rejects, so corpora made mostly of very short
+    /// lines need `TextOptions::verify` turned off or `TextOptions::relax_on_failure` turned on.
+    /// Skipping them instead keeps these near-empty paths out of larger corpora.
+    pub fn skip_short_sentences(mut self, skip_short_sentences: bool) -> Self {
+        self.skip_short_sentences = skip_short_sentences;
+        self
+    }
+
+    /// Sets whether numbers such as `1999` or `3,000` are left out of the training data,
+    /// so the model doesn't generate random figures. A word counts as a number if it is made
+    /// only of digits, `,` and `.`, punctuation following it moves to the word before it.
+    /// See `number_placeholder` to replace numbers instead.
+    pub fn strip_numbers(mut self, strip_numbers: bool) -> Self {
+        self.strip_numbers = strip_numbers;
+        self
+    }
+
+    /// Sets a word that stripped numbers are replaced with, e.g. `<NUM>`, instead of being
+    /// dropped. Only applies with `strip_numbers` on.
+    pub fn number_placeholder(mut self, placeholder: Option<&str>) -> Self {
+        self.number_placeholder = placeholder.map(str::to_string);
+        self
+    }
+
+    /// Sets the strings shown for the begin and end tokens, see `Text::with_sentinels`.
+    pub fn sentinels(mut self, begin: &str, end: &str) -> Self {
+        self.sentinels = (begin.to_string(), end.to_string());
+        self
+    }
+
+    /// Sets how many sentences a first word needs to start for generation to begin with it,
+    /// see `Chain::with_min_begin_weight`. Every first word is kept if none starts that many.
+    pub fn min_begin_weight(mut self, min_begin_weight: i32) -> Self {
+        self.min_begin_weight = min_begin_weight;
+        self
+    }
+
+    /// Sets how probabilities are estimated by `Text::score` and `Text::perplexity`,
+    /// see `Smoothing`. With smoothing, unknown words are scored as unseen transitions.
+    pub fn smoothing(mut self, smoothing: Smoothing) -> Self {
+        self.smoothing = smoothing;
+        self
+    }
+
+    /// Builds a Text instance from the given data.
+    /// # Arguments
+    /// * `data` - A string containing the text data to be processed.
+    /// # Returns
+    /// A new instance of `Text`.
+    pub fn build(&self, data: String) -> Text {
+        self.build_with_tokenizer(data, self.vocab())
+    }
+
+    /// Builds a Text instance that splits and tokenizes words with a custom tokenizer,
+    /// see `Text::new_with_tokenizer`. The normalization setting only applies to `Vocab`,
+    /// so it is ignored here.
+    pub fn build_with_tokenizer<K: Tokenizer>(&self, data: String, tokenizer: K) -> Text<K> {
+        let text = Text::from_builder(self, tokenizer);
+        let lines = self.splitter.split(&data);
+        text.train(lines.into_iter())
+    }
+
+    /// Builds a Text instance from already separated sentences, see `Text::from_sentences`.
+    pub fn build_from_sentences(&self, sentences: impl IntoIterator<Item = String>) -> Text {
+        Text::from_builder(self, self.vocab()).train(sentences.into_iter())
+    }
+
+    /// Builds a Text instance from weighted sentences, see `Text::from_weighted_sentences`.
+    pub fn build_weighted(&self, sentences: impl IntoIterator<Item = (String, i32)>) -> Text {
+        Text::from_builder(self, self.vocab()).train_weighted(sentences.into_iter())
+    }
+
+    /// Returns the empty vocabulary a default Text starts with.
+    fn vocab(&self) -> Vocab {
+        Vocab::with_normalization(self.normalization)
+    }
+}
@@ -1,147 +1,1193 @@
-use std::collections::HashMap;
+use std::borrow::Cow;
+use std::collections::{HashMap, HashSet};
 use std::hash::Hash;
+use std::sync::OnceLock;
 
 use rand::Rng;
+use rand::rngs::ThreadRng;
 
+use crate::error::CombineError;
+
+/// Default number of tokens in a state, i.e. the order of the chain.
 pub const STATE_SIZE: usize = 2;
 
+/// Hard cap on the number of steps a single generation can take.
+pub const MAX_STEPS: usize = 10_000;
+
+/// How often a repeated token is redrawn with `ChainOptions::no_repeat` before it is accepted.
+const REPEAT_RETRIES: usize = 8;
+
 pub type State<T> = Vec<T>;
 pub type Weight<T> = HashMap<T, i32>;
 pub type Model<T> = HashMap<State<T>, Weight<T>>;
 
+/// The choices following a state and their cumulative weights.
+type Compiled<T> = (Vec<T>, Vec<i64>);
+
+/// Which successors are considered when sampling the next token.
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum SamplingStrategy {
+    /// Sample from every successor.
+    #[default]
+    Full,
+    /// Sample from the `k` successors with the highest counts.
+    TopK(usize),
+    /// Sample from the smallest set of highest-count successors whose probabilities add up to at least `p`.
+    TopP(f32),
+}
+
+impl SamplingStrategy {
+    /// Restricts the successors to the ones this strategy samples from.
+    /// At least one successor is always kept if there are any.
+    /// Ties are broken by token, so the result doesn't depend on the `HashMap`'s iteration order.
+    fn apply<T: Ord + Clone>(&self, weights: &HashMap<T, i32>) -> (Vec<T>, Vec<i32>) {
+        let mut pairs: Vec<(&T, i32)> = weights.iter().map(|(t, &w)| (t, w)).collect();
+        pairs.sort_unstable_by(|a, b| a.0.cmp(b.0));
+        if *self != Self::Full {
+            pairs.sort_by_key(|&(_, w)| std::cmp::Reverse(w));
+        }
+
+        let keep = match *self {
+            Self::Full => pairs.len(),
+            Self::TopK(k) => k.max(1),
+            Self::TopP(p) => {
+                let total: i64 = pairs.iter().map(|&(_, w)| w as i64).sum();
+                let mut running: i64 = 0;
+                pairs
+                    .iter()
+                    .position(|&(_, w)| {
+                        running += w as i64;
+                        running as f64 >= p as f64 * total as f64
+                    })
+                    .map_or(pairs.len(), |i| i + 1)
+            }
+        };
+        pairs.truncate(keep);
+
+        pairs.into_iter().map(|(t, w)| (t.clone(), w)).unzip()
+    }
+}
+
+/// How the first token of a sequence is picked, see `Chain::with_begin_strategy`.
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum BeginStrategy<T> {
+    /// Proportionally to how often each token started a run.
+    #[default]
+    Weighted,
+    /// Every token that started a run is equally likely, which makes rare openings more common.
+    Uniform,
+    /// From the given tokens and weights, regardless of the training data.
+    /// Tokens that never started a run lead to a dead end unless the model has such a state,
+    /// and tokens with a weight of 0 or less are never picked.
+    Custom(Vec<(T, i32)>),
+}
+
+/// How probabilities are estimated from the transition counts when scoring a sequence,
+/// see `Chain::with_smoothing`. Generation always follows the counts as they are.
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Smoothing {
+    /// The share of a successor's count in its state's total, unseen transitions are impossible.
+    #[default]
+    None,
+    /// Adds `k` to the count of every possible successor, the distinct tokens that follow any
+    /// state, so unseen transitions and states get a small but nonzero probability.
+    /// `AddK(1.0)` is Laplace smoothing.
+    AddK(f64),
+}
+
+/// A token of a chain whose begin and end markers are kept apart from the data,
+/// so every value of `T` can appear in a run without being mistaken for one. See `Chain::from_words`.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, PartialOrd, Ord)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Token<T> {
+    /// Pads the start of every run.
+    Begin,
+    /// Marks the end of every run.
+    End,
+    /// A value of the data.
+    Word(T),
+}
+
+impl<T> Token<T> {
+    /// Returns the wrapped value, or None for the begin and end markers.
+    pub fn word(&self) -> Option<&T> {
+        match self {
+            Self::Word(word) => Some(word),
+            _ => None,
+        }
+    }
+
+    /// Same as `word`, but takes ownership of the value.
+    pub fn into_word(self) -> Option<T> {
+        match self {
+            Self::Word(word) => Some(word),
+            _ => None,
+        }
+    }
+}
+
+/// Options for generating a sequence from a Chain.
+#[derive(Debug, Clone)]
+pub struct ChainOptions {
+    /// Stop after this many tokens, returning what was generated so far.
+    /// This is applied on top of the chain's own step cap.
+    pub max_tokens: Option<usize>,
+    /// Which successors are considered at each step.
+    pub strategy: SamplingStrategy,
+    /// Rescales the transition counts before sampling by raising them to `1 / temperature`.
+    /// Below `1.0` favors likely successors, above `1.0` flattens the distribution,
+    /// `0.0` or less always picks the most likely successor, the smallest token among equals.
+    pub temperature: f32,
+    /// A soft target for the number of tokens: the end token is made less likely before it
+    /// and increasingly likely after it, so sequences tend to end close to the target.
+    /// Only the end token's weight changes, a state without it still can't end there.
+    pub target_tokens: Option<usize>,
+    /// The end token is never picked before this many tokens were generated,
+    /// unless it is the only way to continue, in which case generation stops there.
+    pub min_tokens: usize,
+    /// When a state has no successors, continue from the states that end in the same
+    /// `order - 1` tokens, then `order - 2` and so on, instead of stopping there.
+    /// Keeps generation going past pruned states or states that aren't part of the model.
+    pub back_off: bool,
+    /// Redraws a token equal to the one before it, so the output doesn't stutter ("the the").
+    /// After a few redraws the repeat is accepted, e.g. when it is the only successor.
+    pub no_repeat: bool,
+}
+
+impl Default for ChainOptions {
+    fn default() -> Self {
+        Self {
+            max_tokens: None,
+            strategy: SamplingStrategy::Full,
+            temperature: 1.0,
+            target_tokens: None,
+            min_tokens: 0,
+            back_off: false,
+            no_repeat: false,
+        }
+    }
+}
+
 /// Chain is used internally to generate text based on a Markov model.
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub struct Chain<T>
 where
-    T: Eq + Hash + Clone + std::fmt::Debug,
+    T: Eq + Hash + Ord + Clone + std::fmt::Debug,
 {
     token_begin: T,
     token_end: T,
     model: Model<T>,
     begin_choices: Vec<T>,
-    begin_weights: Vec<i32>,
+    begin_weights: Vec<i64>,
+    max_steps: usize,
+    order: usize,
+    precomputed: Option<HashMap<State<T>, Compiled<T>>>,
+    begin_strategy: BeginStrategy<T>,
+    /// Begin choices seen fewer times than this are left out, see `with_min_begin_weight`.
+    min_begin_weight: i32,
+    smoothing: Smoothing,
+    /// The number of distinct successors in the model, only kept up to date while smoothing.
+    successor_vocab: usize,
+    /// The successors of every state added up by each shorter suffix of it, for back-off.
+    /// Built the first time generation backs off and dropped whenever the model changes.
+    suffix_index: OnceLock<HashMap<State<T>, Weight<T>>>,
 }
 
 impl<T> Chain<T>
 where
-    T: Eq + Hash + Clone + std::fmt::Debug,
+    T: Eq + Hash + Ord + Clone + std::fmt::Debug,
 {
     /// Creates an empty Chain.
-    pub fn default(begin: T, end: T) -> Self {
+    /// `order` is the number of tokens in a state, it is clamped to at least 1.
+    /// `begin` and `end` must differ from each other and from every token of the data,
+    /// use `Token` and `Chain::from_words` when no such values can be spared.
+    pub fn default(begin: T, end: T, order: usize) -> Self {
         Self {
             token_begin: begin,
             token_end: end,
             model: Model::new(),
             begin_choices: Vec::new(),
             begin_weights: Vec::new(),
+            max_steps: MAX_STEPS,
+            order: order.max(1),
+            precomputed: None,
+            begin_strategy: BeginStrategy::Weighted,
+            min_begin_weight: 0,
+            smoothing: Smoothing::None,
+            successor_vocab: 0,
+            suffix_index: OnceLock::new(),
         }
     }
 
     /// Accumulate a list of integers into a cumulative distribution.
-    fn accumulate(ns: &[i32]) -> Vec<i32> {
-        let mut numbers: Vec<i32> = Vec::with_capacity(ns.len());
-        let mut total = ns[0];
+    /// An empty list gives an empty distribution, which callers treat as a dead end.
+    /// The totals are `i64`, so even counts near `i32::MAX` can't overflow them.
+    fn accumulate(ns: &[i32]) -> Vec<i64> {
+        let mut numbers: Vec<i64> = Vec::with_capacity(ns.len());
+        let mut total: i64 = 0;
         for &n in ns {
+            total += n as i64;
             numbers.push(total);
-            total += n; // yes this is on purpose
         }
         numbers
     }
 
     /// Compile the next possible words and their cumulative weights.
-    fn compile_next(data: &Weight<T>) -> (Vec<T>, Vec<i32>) {
-        let words: Vec<T> = data.keys().cloned().collect();
-        let weights: Vec<i32> = data.values().cloned().collect();
-        let cum: Vec<i32> = Self::accumulate(&weights);
+    /// The successors are sorted by token, so a seeded draw picks the same token on every run
+    /// regardless of the `HashMap`'s iteration order.
+    fn compile_next(data: &Weight<T>) -> Compiled<T> {
+        let mut pairs: Vec<(&T, i32)> = data.iter().map(|(t, &w)| (t, w)).collect();
+        pairs.sort_unstable_by(|a, b| a.0.cmp(b.0));
+        let (words, weights): (Vec<T>, Vec<i32>) =
+            pairs.into_iter().map(|(t, w)| (t.clone(), w)).unzip();
+        let cum: Vec<i64> = Self::accumulate(&weights);
         (words, cum)
     }
 
-    /// Refer to python's `bisect.bisect`, this is more or less the same.
-    fn bisect_right<X: Ord>(slice: &[X], x: &X) -> usize {
-        match slice.binary_search(x) {
-            Ok(idx) => idx + 1,
-            Err(idx) => idx,
+    /// Returns the weight of each begin choice, undoing the cumulative sums.
+    fn begin_distribution(&self) -> HashMap<&T, i64> {
+        let mut previous = 0;
+        self.begin_choices
+            .iter()
+            .zip(&self.begin_weights)
+            .map(|(choice, &cum)| {
+                let weight = cum - previous;
+                previous = cum;
+                (choice, weight)
+            })
+            .collect()
+    }
+}
+
+/// Generator lazily yields the tokens of a sequence generated by a `Chain`.
+/// Created by `Chain::iter` and its variants.
+pub struct Generator<'a, T, R = ThreadRng>
+where
+    T: Eq + Hash + Ord + Clone + std::fmt::Debug,
+{
+    chain: &'a Chain<T>,
+    state: State<T>,
+    options: ChainOptions,
+    rng: R,
+    remaining: usize,
+    /// The number of tokens yielded so far.
+    produced: usize,
+}
+
+impl<T, R> Generator<'_, T, R>
+where
+    T: Eq + Hash + Ord + Clone + std::fmt::Debug,
+{
+    /// Returns the current state, i.e. the last tokens that were generated.
+    pub fn state(&self) -> &State<T> {
+        &self.state
+    }
+
+    /// Draws the token following the current state, which may be the end token.
+    fn draw(&mut self) -> Option<T>
+    where
+        R: Rng,
+    {
+        if self.options.target_tokens.is_some() || self.produced < self.options.min_tokens {
+            self.chain
+                .next_shaped(&self.state, &self.options, self.produced, &mut self.rng)
+        } else {
+            self.chain
+                .next_with_options(&self.state, &self.options, &mut self.rng)
+        }
+        .or_else(|| {
+            self.options.back_off.then(|| {
+                self.chain
+                    .next_backed_off(&self.state, &self.options, &mut self.rng)
+            })?
+        })
+    }
+}
+
+impl<T, R> Iterator for Generator<'_, T, R>
+where
+    T: Eq + Hash + Ord + Clone + std::fmt::Debug,
+    R: Rng,
+{
+    type Item = T;
+
+    fn next(&mut self) -> Option<T> {
+        if self.remaining == 0 {
+            return None;
         }
+        let mut next_word = self.draw();
+        if self.options.no_repeat {
+            let previous = self
+                .state
+                .last()
+                .filter(|&t| *t != self.chain.token_begin)
+                .cloned();
+            for _ in 0..REPEAT_RETRIES {
+                if previous.is_none() || next_word != previous {
+                    break;
+                }
+                next_word = self.draw();
+            }
+        }
+        let Some(next_word) = next_word.filter(|w| *w != self.chain.token_end) else {
+            self.remaining = 0;
+            return None;
+        };
+        self.remaining -= 1;
+        self.produced += 1;
+        self.state.remove(0);
+        self.state.push(next_word.clone());
+        Some(next_word)
+    }
+}
+
+/// Compares the logical model of two chains: their transitions, begin/end tokens,
+/// the weight of each begin choice and the settings that shape the begin distribution
+/// and scoring. Whether the model is precomputed doesn't matter.
+impl<T> PartialEq for Chain<T>
+where
+    T: Eq + Hash + Ord + Clone + std::fmt::Debug,
+{
+    fn eq(&self, other: &Self) -> bool {
+        self.order == other.order
+            && self.token_begin == other.token_begin
+            && self.token_end == other.token_end
+            && self.model == other.model
+            && self.begin_strategy == other.begin_strategy
+            && self.min_begin_weight == other.min_begin_weight
+            && self.smoothing == other.smoothing
+            && self.begin_distribution() == other.begin_distribution()
+    }
+}
+
+impl<T> Eq for Chain<T> where T: Eq + Hash + Ord + Clone + std::fmt::Debug {}
+
+/// A one-line summary of the model's size, e.g. `Chain{order=2, states=1234, transitions=5678}`.
+/// Use `Debug` to print the whole model.
+impl<T> std::fmt::Display for Chain<T>
+where
+    T: Eq + Hash + Ord + Clone + std::fmt::Debug,
+{
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "Chain{{order={}, states={}, transitions={}}}",
+            self.order,
+            self.state_count(),
+            self.transition_count()
+        )
     }
 }
 
 impl<T> Chain<T>
 where
-    T: Eq + Hash + Clone + std::fmt::Debug,
+    T: Eq + Hash + Ord + Clone + std::fmt::Debug,
 {
     /// Creates a new Chain from the given data.
     /// # Arguments
     /// * `data` - A reference to a slice of vectors of strings, where each vector represents a sequence of words.
+    /// * `begin` - The token used to pad the start of each sequence.
+    /// * `end` - The token used to mark the end of each sequence.
+    /// * `order` - The number of tokens in a state, usually `STATE_SIZE`.
+    ///   Lower orders give more random output, higher orders stay closer to the source.
     /// # Returns
     /// A new instance of `Chain`.
-    pub fn new(data: &[Vec<T>], begin: T, end: T) -> Self {
-        let mut chain = Self::default(begin, end);
+    pub fn new(data: &[Vec<T>], begin: T, end: T, order: usize) -> Self {
+        let mut chain = Self::default(begin, end, order);
         chain.model = chain.build(data);
         chain.compute();
         chain
     }
 
+    /// Creates a new Chain where each run counts as many times as its weight,
+    /// so more representative runs influence the model more.
+    /// A weight of 1 for every run gives the same chain as `new`.
+    /// # Arguments
+    /// * `data` - The runs paired with their weights, runs with a weight of 0 or less are skipped.
+    /// * `begin` - The token used to pad the start of each sequence.
+    /// * `end` - The token used to mark the end of each sequence.
+    /// * `order` - The number of tokens in a state.
+    /// # Returns
+    /// A new instance of `Chain`.
+    pub fn new_weighted(data: &[(Vec<T>, i32)], begin: T, end: T, order: usize) -> Self {
+        let mut chain = Self::default(begin, end, order);
+        let mut model = Model::new();
+        for (run, weight) in data {
+            chain.insert_run(&mut model, run, *weight);
+        }
+        chain.model = model;
+        chain.compute();
+        chain
+    }
+
+    /// Creates a new Chain where every run is tagged with a category, e.g. the section it comes from.
+    /// Runs are trained as in `new`, and can additionally be started from their category
+    /// with `generate_from_category`, which only begins the way runs of that category begin.
+    /// # Arguments
+    /// * `data` - The runs paired with their category token, which must not appear inside any run.
+    /// * `begin` - The token used to pad the start of each sequence.
+    /// * `end` - The token used to mark the end of each sequence.
+    /// * `order` - The number of tokens in a state.
+    /// # Returns
+    /// A new instance of `Chain`.
+    pub fn new_categorized(data: &[(T, Vec<T>)], begin: T, end: T, order: usize) -> Self {
+        let mut chain = Self::default(begin, end, order);
+        let mut model = Model::new();
+        for (category, run) in data {
+            chain.insert_categorized(&mut model, category, run);
+        }
+        chain.model = model;
+        chain.compute();
+        chain
+    }
+
+    /// Creates a new Chain from runs produced lazily, e.g. read from a stream.
+    /// # Arguments
+    /// * `runs` - The sequences to train on, consumed one at a time.
+    /// * `begin` - The token used to pad the start of each sequence.
+    /// * `end` - The token used to mark the end of each sequence.
+    /// * `order` - The number of tokens in a state.
+    /// # Returns
+    /// A new instance of `Chain`.
+    pub fn from_runs(
+        runs: impl IntoIterator<Item = Vec<T>>,
+        begin: T,
+        end: T,
+        order: usize,
+    ) -> Self {
+        let mut chain = Self::default(begin, end, order);
+        let mut model = Model::new();
+        for run in runs {
+            chain.insert_run(&mut model, &run, 1);
+        }
+        chain.model = model;
+        chain.compute();
+        chain
+    }
+
+    /// Precomputes the cumulative distribution of every state, not just the begin state,
+    /// so `next` becomes a lookup and a bisect. This trades memory for speed
+    /// and is kept up to date as the model changes.
+    pub fn with_precomputed(mut self) -> Self {
+        self.precomputed = Some(HashMap::new());
+        self.compute();
+        self
+    }
+
+    /// Compiles the distributions of every state in the model.
+    fn compile_all(&self) -> HashMap<State<T>, Compiled<T>> {
+        self.model
+            .iter()
+            .map(|(state, weights)| (state.clone(), Self::compile_next(weights)))
+            .collect()
+    }
+
+    /// Returns the number of tokens in a state.
+    pub fn order(&self) -> usize {
+        self.order
+    }
+
+    /// Sets the maximum number of steps a single generation can take.
+    /// Generation stops once this is reached and returns what it has so far,
+    /// which keeps models that never reach the end token from looping forever.
+    pub fn with_max_steps(mut self, max_steps: usize) -> Self {
+        self.max_steps = max_steps;
+        self
+    }
+
+    /// Sets how the first token of a sequence is picked, see `BeginStrategy`.
+    /// Only the begin step is affected, every later step still follows the transition counts.
+    pub fn with_begin_strategy(mut self, strategy: BeginStrategy<T>) -> Self {
+        self.begin_strategy = strategy;
+        self.compute();
+        self
+    }
+
+    /// Returns how the first token of a sequence is picked.
+    pub fn begin_strategy(&self) -> &BeginStrategy<T> {
+        &self.begin_strategy
+    }
+
+    /// Leaves tokens that started fewer than `min_weight` runs out of the begin choices,
+    /// which keeps one-off openings from starting a sequence.
+    /// If no token started that many runs, every one of them is kept instead.
+    /// `BeginStrategy::Custom` weights are used as given, this only filters the trained counts.
+    pub fn with_min_begin_weight(mut self, min_weight: i32) -> Self {
+        self.min_begin_weight = min_weight;
+        self.compute();
+        self
+    }
+
+    /// Returns the minimum count a token needs to be a begin choice.
+    pub fn min_begin_weight(&self) -> i32 {
+        self.min_begin_weight
+    }
+
+    /// Sets how probabilities are estimated when scoring, see `Smoothing`.
+    /// With `Smoothing::AddK`, `sequence_log_prob` gives a finite value for every sequence.
+    pub fn with_smoothing(mut self, smoothing: Smoothing) -> Self {
+        self.smoothing = smoothing;
+        self.compute();
+        self
+    }
+
+    /// Returns how probabilities are estimated when scoring.
+    pub fn smoothing(&self) -> Smoothing {
+        self.smoothing
+    }
+
+    /// Copies the settings that aren't learned from the data from another chain,
+    /// used when a chain is retrained from scratch.
+    #[cfg(feature = "text")]
+    pub(crate) fn with_settings_of(mut self, other: &Self) -> Self {
+        self.min_begin_weight = other.min_begin_weight;
+        self.smoothing = other.smoothing;
+        self.compute();
+        self
+    }
+
+    /// Combines several chains into one, like markovify's `combine`.
+    /// Each chain's transition counts are scaled by its weight (rounded to the nearest integer)
+    /// and summed with the others, counts that would exceed `i32::MAX` are capped there.
+    /// # Arguments
+    /// * `chains` - The chains to combine, they must share the same begin/end tokens and order.
+    /// * `weights` - One weight per chain.
+    /// # Returns
+    /// The combined chain, or a `CombineError` if the inputs don't fit together.
+    pub fn combine(chains: &[&Chain<T>], weights: &[f64]) -> Result<Self, CombineError> {
+        let Some(first) = chains.first() else {
+            return Err(CombineError::Empty);
+        };
+        if chains.len() != weights.len() {
+            return Err(CombineError::WeightCount);
+        }
+
+        let mut combined = Self::default(
+            first.token_begin.clone(),
+            first.token_end.clone(),
+            first.order,
+        );
+        combined.max_steps = first.max_steps;
+
+        for (chain, &weight) in chains.iter().zip(weights) {
+            if chain.token_begin != first.token_begin || chain.token_end != first.token_end {
+                return Err(CombineError::TokenMismatch);
+            }
+            if chain.order != first.order {
+                return Err(CombineError::OrderMismatch);
+            }
+            Self::merge_models(&mut combined.model, &chain.model, weight);
+        }
+
+        combined.compute();
+        Ok(combined)
+    }
+
+    /// Adds the transitions of one model into another, scaling their counts by `weight`.
+    /// Transitions that round down to nothing are left out.
+    fn merge_models(into: &mut Model<T>, from: &Model<T>, weight: f64) {
+        for (state, weights) in from {
+            for (follow, &count) in weights {
+                let scaled = (count as f64 * weight).round() as i32;
+                if scaled <= 0 {
+                    continue;
+                }
+                let count = into
+                    .entry(state.clone())
+                    .or_default()
+                    .entry(follow.clone())
+                    .or_insert(0);
+                *count = count.saturating_add(scaled);
+            }
+        }
+    }
+
     /// Builds the Markov model from the provided data.
     fn build(&self, data: &[Vec<T>]) -> Model<T> {
         let mut model: Model<T> = HashMap::new();
 
         for run in data {
-            let mut items: Vec<&T> = vec![&self.token_begin; STATE_SIZE];
-            items.extend(run);
-            items.push(&self.token_end);
+            self.insert_run(&mut model, run, 1);
+        }
 
-            for i in 0..run.len() + 1 {
-                let state: State<T> = items[i..i + STATE_SIZE].iter().cloned().cloned().collect();
-                let follow: &T = items[i + STATE_SIZE];
+        model
+    }
 
-                model
-                    .entry(state)
-                    .or_default()
-                    .entry(follow.clone())
-                    .and_modify(|e| *e += 1)
-                    .or_insert(1);
+    /// Adds the transitions of a single run to the model, padding it with the begin and end tokens.
+    /// Each transition is counted `weight` times.
+    fn insert_run(&self, model: &mut Model<T>, run: &[T], weight: i32) {
+        self.insert_padded(model, &self.token_begin, run, weight, run.len() + 1);
+    }
+
+    /// Adds the first `limit` transitions of a run padded with `pad` instead of the begin token.
+    fn insert_padded(&self, model: &mut Model<T>, pad: &T, run: &[T], weight: i32, limit: usize) {
+        if weight <= 0 {
+            return;
+        }
+
+        let mut items: Vec<&T> = vec![pad; self.order];
+        items.extend(run);
+        items.push(&self.token_end);
+
+        for i in 0..limit.min(run.len() + 1) {
+            let state: State<T> = items[i..i + self.order].iter().cloned().cloned().collect();
+            let follow: &T = items[i + self.order];
+
+            model
+                .entry(state)
+                .or_default()
+                .entry(follow.clone())
+                .and_modify(|e| *e = e.saturating_add(weight))
+                .or_insert(weight);
+        }
+    }
+
+    /// Adds a run that can also be started from its category, see `new_categorized`.
+    fn insert_categorized(&self, model: &mut Model<T>, category: &T, run: &[T]) {
+        self.insert_run(model, run, 1);
+        // only the states that still contain the padding differ from the plain run,
+        // everything after them is shared with it.
+        self.insert_padded(model, category, run, 1, self.order);
+    }
+
+    /// Trains the chain on one more run, for updating a model incrementally.
+    /// # Arguments
+    /// * `run` - A sequence of tokens, padded the same way as in `new`.
+    pub fn add_run(&mut self, run: &[T]) {
+        let mut model = std::mem::take(&mut self.model);
+        self.insert_run(&mut model, run, 1);
+        self.model = model;
+        self.compute();
+    }
+
+    /// Trains the chain on one more run after scaling every existing count by `decay`,
+    /// so older runs count for less and repeated calls give an exponentially decaying model.
+    /// Scaled counts are rounded, transitions that reach zero and states left without
+    /// successors are removed. As counts stay integers, small counts stop decaying once
+    /// scaling no longer changes them after rounding, e.g. 1 with a decay of 0.5 or more.
+    /// # Arguments
+    /// * `run` - A sequence of tokens, padded the same way as in `new`.
+    /// * `decay` - The factor existing counts are multiplied by, clamped to `0.0..=1.0`.
+    pub fn add_run_decayed(&mut self, run: &[T], decay: f32) {
+        let decay = decay.clamp(0.0, 1.0) as f64;
+        if decay < 1.0 {
+            self.model.retain(|_, weights| {
+                weights.retain(|_, count| {
+                    *count = (*count as f64 * decay).round() as i32;
+                    *count > 0
+                });
+                !weights.is_empty()
+            });
+        }
+        self.add_run(run);
+    }
+
+    /// Trains the chain on more runs, refreshing the begin caches once at the end.
+    /// # Arguments
+    /// * `runs` - A slice of token sequences, padded the same way as in `new`.
+    pub fn add_runs(&mut self, runs: &[Vec<T>]) {
+        let mut model = std::mem::take(&mut self.model);
+        for run in runs {
+            self.insert_run(&mut model, run, 1);
+        }
+        self.model = model;
+        self.compute();
+    }
+
+    /// Removes every transition seen fewer than `min_count` times,
+    /// along with the states that are left without any successors.
+    /// Generation treats the removed states as dead ends and stops there.
+    /// # Arguments
+    /// * `min_count` - The minimum count a transition needs to be kept.
+    pub fn prune(&mut self, min_count: i32) {
+        self.model.retain(|_, weights| {
+            weights.retain(|_, count| *count >= min_count);
+            !weights.is_empty()
+        });
+        self.compute();
+    }
+
+    /// Shrinks the model to at most `max_states` states, keeping the ones with the highest
+    /// total outgoing count. The begin state is always kept and counts towards the cap.
+    /// Generation treats the removed states as dead ends and stops there.
+    /// # Arguments
+    /// * `max_states` - The maximum number of states to keep.
+    pub fn truncate_to(&mut self, max_states: usize) {
+        if self.model.len() <= max_states {
+            return;
+        }
+
+        let begin_state = self.begin_state();
+        let begin = self.model.remove_entry(&begin_state);
+        let mut states: Vec<(State<T>, Weight<T>)> =
+            std::mem::take(&mut self.model).into_iter().collect();
+        states.sort_by_key(|(_, weights)| {
+            std::cmp::Reverse(weights.values().map(|&c| c as i64).sum::<i64>())
+        });
+        states.truncate(max_states.saturating_sub(begin.is_some() as usize));
+
+        self.model = states.into_iter().chain(begin).collect();
+        self.compute();
+    }
+
+    /// Trains the chain on one more run tagged with a category, see `new_categorized`.
+    pub fn add_categorized_run(&mut self, category: T, run: &[T]) {
+        let mut model = std::mem::take(&mut self.model);
+        self.insert_categorized(&mut model, &category, run);
+        self.model = model;
+        self.compute();
+    }
+
+    /// Removes all transitions while keeping the tokens, settings and allocations,
+    /// so the chain can be retrained with `add_run` without building a new one.
+    pub fn clear(&mut self) {
+        self.model.clear();
+        self.begin_choices.clear();
+        self.begin_weights.clear();
+        self.successor_vocab = 0;
+        self.suffix_index = OnceLock::new();
+        if let Some(precomputed) = &mut self.precomputed {
+            precomputed.clear();
+        }
+    }
+
+    /// Returns the token used to pad the start of each run.
+    pub fn begin_token(&self) -> &T {
+        &self.token_begin
+    }
+
+    /// Returns the token used to mark the end of each run.
+    pub fn end_token(&self) -> &T {
+        &self.token_end
+    }
+
+    /// Returns the number of states in the model.
+    pub fn state_count(&self) -> usize {
+        self.model.len()
+    }
+
+    /// Returns the number of distinct transitions in the model, i.e. the number of
+    /// (state, successor) pairs regardless of how often each was seen.
+    pub fn transition_count(&self) -> usize {
+        self.model.values().map(|weights| weights.len()).sum()
+    }
+
+    /// Returns the successors of a state with their transition counts,
+    /// or None if the state is not part of the model.
+    pub fn successors(&self, state: &State<T>) -> Option<&Weight<T>> {
+        self.model.get(state)
+    }
+
+    /// Returns the model itself, for changes the other methods don't cover.
+    /// The cached begin and precomputed distributions are not updated as it changes,
+    /// call `recompute` once done, otherwise generation keeps sampling from the old ones.
+    /// Counts must be positive, `recompute` drops the transitions whose count isn't.
+    pub fn model_mut(&mut self) -> &mut Model<T> {
+        &mut self.model
+    }
+
+    /// Rebuilds the cached begin and precomputed distributions from the model.
+    /// Every method of `Chain` keeps them up to date itself, this is only needed
+    /// after changing the model through `model_mut`.
+    /// Transitions with a count of 0 or less are removed, along with the states left empty.
+    pub fn recompute(&mut self) {
+        self.model.retain(|_, weights| {
+            weights.retain(|_, count| *count > 0);
+            !weights.is_empty()
+        });
+        self.compute();
+    }
+
+    /// Returns the most likely successors of a state, most likely first.
+    /// # Arguments
+    /// * `state` - The state to look up.
+    /// * `n` - The maximum number of successors to return.
+    /// # Returns
+    /// Up to `n` successors with their probabilities, empty if the state is not part of the model.
+    pub fn top_successors(&self, state: &State<T>, n: usize) -> Vec<(T, f64)> {
+        let Some(weights) = self.model.get(state) else {
+            return Vec::new();
+        };
+        let total: i64 = weights.values().map(|&w| w as i64).sum();
+        let mut top: Vec<(T, f64)> = weights
+            .iter()
+            .map(|(token, &w)| (token.clone(), w as f64 / total as f64))
+            .collect();
+        top.sort_by(|a, b| b.1.total_cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+        top.truncate(n);
+        top
+    }
+
+    /// Computes the Shannon entropy of a state's successors, i.e. how unpredictable the next token is.
+    /// # Arguments
+    /// * `state` - The state to look up.
+    /// # Returns
+    /// The entropy in bits, 0 for a single successor and 1 for two equally likely ones,
+    /// or None if the state is not part of the model.
+    pub fn state_entropy(&self, state: &State<T>) -> Option<f64> {
+        let weights = self.model.get(state)?;
+        let total: i64 = weights.values().map(|&w| w as i64).sum();
+        if total <= 0 {
+            return None;
+        }
+        let entropy = weights
+            .values()
+            .filter(|&&w| w > 0)
+            .map(|&w| {
+                let p = w as f64 / total as f64;
+                p * (1.0 / p).log2()
+            })
+            .sum();
+        Some(entropy)
+    }
+
+    /// Computes the average of `state_entropy` over every state of the model,
+    /// each state counting the same.
+    /// # Returns
+    /// The mean entropy in bits, 0 for an empty model.
+    pub fn mean_entropy(&self) -> f64 {
+        let entropies: Vec<f64> = self
+            .model
+            .keys()
+            .filter_map(|state| self.state_entropy(state))
+            .collect();
+        if entropies.is_empty() {
+            return 0.0;
+        }
+        entropies.iter().sum::<f64>() / entropies.len() as f64
+    }
+
+    /// Checks whether the model has no transitions at all.
+    pub fn is_empty(&self) -> bool {
+        self.model.is_empty()
+    }
+
+    /// Checks whether the given state is part of the model.
+    pub fn contains_state(&self, state: &State<T>) -> bool {
+        self.model.contains_key(state)
+    }
+
+    /// Finds the states generation can reach but not leave, e.g. after `prune` or `truncate_to`
+    /// removed everything that followed them. Reaching one stops generation before the end token.
+    /// # Returns
+    /// The dead-end states in sorted order, empty if every reachable state can continue.
+    pub fn dead_end_states(&self) -> Vec<State<T>> {
+        let is_live = |state: &State<T>| self.model.get(state).is_some_and(|w| !w.is_empty());
+        let mut dead: HashSet<State<T>> = self
+            .model
+            .keys()
+            .filter(|state| !is_live(state))
+            .cloned()
+            .collect();
+        for (state, weights) in &self.model {
+            for next in weights.keys().filter(|&next| *next != self.token_end) {
+                let mut to = state.clone();
+                to.remove(0);
+                to.push(next.clone());
+                if !is_live(&to) {
+                    dead.insert(to);
+                }
             }
         }
 
-        model
+        let mut dead: Vec<State<T>> = dead.into_iter().collect();
+        dead.sort_unstable();
+        dead
     }
 
     /// Returns the initial state of the Markov chain.
     fn begin_state(&self) -> State<T> {
-        vec![self.token_begin.clone(); STATE_SIZE]
+        vec![self.token_begin.clone(); self.order]
+    }
+
+    /// Checks whether a state is the begin state, without building one to compare against.
+    fn is_begin_state(&self, state: &State<T>) -> bool {
+        state.len() == self.order && state.iter().all(|t| *t == self.token_begin)
+    }
+
+    /// Returns the begin state's successors that are seen at least `min_begin_weight` times,
+    /// or all of them if none are.
+    fn common_openers<'a>(&self, weights: &'a Weight<T>) -> Cow<'a, Weight<T>> {
+        if self.min_begin_weight <= 1
+            || weights.values().all(|&count| count < self.min_begin_weight)
+        {
+            return Cow::Borrowed(weights);
+        }
+        Cow::Owned(
+            weights
+                .iter()
+                .filter(|&(_, &count)| count >= self.min_begin_weight)
+                .map(|(t, &count)| (t.clone(), count))
+                .collect(),
+        )
     }
 
     /// Precomputes the choices and weights for the initial state.
     fn compute(&mut self) {
+        self.suffix_index = OnceLock::new();
         let begin_state = self.begin_state();
-        if let Some(weights) = self.model.get(&begin_state) {
-            let (choices, cum) = Self::compile_next(weights);
+        let openers = self.model.get(&begin_state).map(|w| self.common_openers(w));
+        let begin = match (&self.begin_strategy, openers.as_deref()) {
+            (BeginStrategy::Custom(weights), _) => {
+                let weights: Weight<T> = weights
+                    .iter()
+                    .filter(|&&(_, w)| w > 0)
+                    .map(|(t, w)| (t.clone(), *w))
+                    .collect();
+                Some(Self::compile_next(&weights))
+            }
+            (BeginStrategy::Uniform, Some(weights)) => {
+                let weights: Weight<T> = weights.keys().map(|t| (t.clone(), 1)).collect();
+                Some(Self::compile_next(&weights))
+            }
+            (_, weights) => weights.map(Self::compile_next),
+        };
+        if let Some((choices, cum)) = begin {
             self.begin_choices = choices;
             self.begin_weights = cum;
+        } else {
+            self.begin_choices.clear();
+            self.begin_weights.clear();
         }
+        if self.precomputed.is_some() {
+            self.precomputed = Some(self.compile_all());
+        }
+        self.successor_vocab = match self.smoothing {
+            Smoothing::None => 0,
+            Smoothing::AddK(_) => self
+                .model
+                .values()
+                .flat_map(|w| w.keys())
+                .collect::<HashSet<&T>>()
+                .len(),
+        };
     }
 
     /// Moves to the next state based on the current state.
     /// # Arguments
     /// * `state` - A reference to the current state of the Markov chain.
     /// # Returns
-    /// A <T> representing the next token in the sequence.
-    pub fn next(&self, state: &State<T>) -> T {
-        let (mut choices, mut cumdist) = (self.begin_choices.clone(), self.begin_weights.clone());
-        if state != &self.begin_state() {
-            // FIXME: This is bad
-            choices.clear();
-            cumdist.clear();
-            let mut weights: Vec<i32> = Vec::new();
-            for (word, weight) in self.model.get(state).unwrap() {
-                choices.push(word.clone());
-                weights.push(*weight);
+    /// A <T> representing the next token in the sequence,
+    /// or None if the state is not part of the model or has no transitions.
+    pub fn next(&self, state: &State<T>) -> Option<T> {
+        self.next_with_rng(state, &mut rand::rng())
+    }
+
+    /// Same as `next`, but draws from the given random number generator,
+    /// which makes the output reproducible when the generator is seeded.
+    pub fn next_with_rng<R: Rng + ?Sized>(&self, state: &State<T>, rng: &mut R) -> Option<T> {
+        if self.is_begin_state(state) {
+            return Self::sample(&self.begin_choices, &self.begin_weights, rng);
+        }
+        if let Some(precomputed) = &self.precomputed {
+            let (choices, cumdist) = precomputed.get(state)?;
+            return Self::sample(choices, cumdist, rng);
+        }
+        let (choices, cumdist) = Self::compile_next(self.model.get(state)?);
+        Self::sample(&choices, &cumdist, rng)
+    }
+
+    /// Same as `next_with_rng`, but only samples from the successors allowed by the options.
+    pub fn next_with_options<R: Rng + ?Sized>(
+        &self,
+        state: &State<T>,
+        options: &ChainOptions,
+        rng: &mut R,
+    ) -> Option<T> {
+        // the begin strategy replaces the counts of the begin step, so there is nothing to rescale.
+        if (options.strategy == SamplingStrategy::Full && options.temperature == 1.0)
+            || (self.begin_strategy != BeginStrategy::Weighted && self.is_begin_state(state))
+        {
+            return self.next_with_rng(state, rng);
+        }
+        let weights = self.model.get(state)?;
+        let weights = if self.is_begin_state(state) {
+            self.common_openers(weights)
+        } else {
+            Cow::Borrowed(weights)
+        };
+        Self::sample_with_options(&weights, options, rng)
+    }
+
+    /// Draws one of the successors, restricted and rescaled according to the options.
+    fn sample_with_options<R: Rng + ?Sized>(
+        weights: &Weight<T>,
+        options: &ChainOptions,
+        rng: &mut R,
+    ) -> Option<T> {
+        let (choices, weights) = options.strategy.apply(weights);
+        if options.temperature == 1.0 {
+            return Self::sample(&choices, &Self::accumulate(&weights), rng);
+        }
+
+        if options.temperature <= 0.0 {
+            // ties go to the smallest token, the same as `generate_greedy`.
+            let best = choices
+                .iter()
+                .zip(&weights)
+                .max_by(|a, b| a.1.cmp(b.1).then_with(|| b.0.cmp(a.0)))?;
+            return Some(best.0.clone());
+        }
+        let scaled = Self::tempered(&weights, options.temperature);
+        Self::sample_scaled(&choices, &scaled, rng)
+    }
+
+    /// Draws the next token from every state ending in the longest part of `state` that exists,
+    /// for states that have no successors of their own, see `ChainOptions::back_off`.
+    /// The counts of those states are added up, as if the chain had a lower order.
+    fn next_backed_off<R: Rng + ?Sized>(
+        &self,
+        state: &State<T>,
+        options: &ChainOptions,
+        rng: &mut R,
+    ) -> Option<T> {
+        let index = self.suffix_index.get_or_init(|| self.build_suffix_index());
+        for keep in (1..state.len()).rev() {
+            if let Some(merged) = index
+                .get(&state[state.len() - keep..])
+                .filter(|merged| !merged.is_empty())
+            {
+                return Self::sample_with_options(merged, options, rng);
             }
-            cumdist = Self::accumulate(&weights);
         }
-        let r: f32 = rand::rng().random_range(0.0..1.0) * (*cumdist.last().unwrap() as f32);
-        let r_i32 = r as i32;
-        choices[Self::bisect_right(&cumdist, &r_i32)].clone()
+        None
+    }
+
+    /// Adds up the successors of every state under each of its suffixes shorter than a state,
+    /// so backing off looks up one entry instead of scanning the whole model.
+    fn build_suffix_index(&self) -> HashMap<State<T>, Weight<T>> {
+        let mut index: HashMap<State<T>, Weight<T>> = HashMap::new();
+        for (state, weights) in &self.model {
+            for keep in 1..state.len() {
+                let merged = index
+                    .entry(state[state.len() - keep..].to_vec())
+                    .or_default();
+                for (next, &count) in weights {
+                    let merged = merged.entry(next.clone()).or_insert(0);
+                    *merged = merged.saturating_add(count);
+                }
+            }
+        }
+        index
+    }
+
+    /// Draws the first token like `next_with_options`, but never the end token the begin state
+    /// has after empty runs, unless it is the only choice, in which case generation stops there.
+    fn next_begin_without_end<R: Rng + ?Sized>(
+        &self,
+        state: &State<T>,
+        options: &ChainOptions,
+        rng: &mut R,
+    ) -> Option<T> {
+        let weighted = self.begin_strategy == BeginStrategy::Weighted;
+        let mut weights: Weight<T> = if weighted {
+            self.common_openers(self.model.get(state)?).into_owned()
+        } else {
+            self.begin_distribution()
+                .into_iter()
+                .map(|(t, w)| (t.clone(), w as i32))
+                .collect()
+        };
+        if weights.remove(&self.token_end).is_some() && weights.is_empty() {
+            return Some(self.token_end.clone());
+        }
+        if weighted {
+            Self::sample_with_options(&weights, options, rng)
+        } else {
+            let (choices, cumdist) = Self::compile_next(&weights);
+            Self::sample(&choices, &cumdist, rng)
+        }
+    }
+
+    /// Same as `next_with_options`, but scales the end token's weight depending on how many
+    /// tokens were `produced` so far, see `ChainOptions::min_tokens` and `ChainOptions::target_tokens`.
+    fn next_shaped<R: Rng + ?Sized>(
+        &self,
+        state: &State<T>,
+        options: &ChainOptions,
+        produced: usize,
+        rng: &mut R,
+    ) -> Option<T> {
+        if self.is_begin_state(state) {
+            if produced < options.min_tokens {
+                return self.next_begin_without_end(state, options, rng);
+            }
+            return self.next_with_options(state, options, rng);
+        }
+        let (choices, weights) = options.strategy.apply(self.model.get(state)?);
+
+        let bias = match options.target_tokens {
+            _ if produced < options.min_tokens => 0.0,
+            // halves or doubles the end token's weight with every eighth of the target.
+            Some(target) => {
+                let spread = (target as f64 / 8.0).max(1.0);
+                2f64.powf((produced as f64 - target as f64) / spread)
+            }
+            None => 1.0,
+        };
+        let bias_of = |choice: &T| if *choice == self.token_end { bias } else { 1.0 };
+
+        if options.temperature <= 0.0 {
+            let best = choices
+                .iter()
+                .zip(&weights)
+                .map(|(choice, &w)| (choice, w as f64 * bias_of(choice)))
+                .filter(|&(_, w)| w > 0.0)
+                .max_by(|a, b| a.1.total_cmp(&b.1).then_with(|| b.0.cmp(a.0)))?;
+            return Some(best.0.clone());
+        }
+        let scaled: Vec<f64> = choices
+            .iter()
+            .zip(Self::tempered(&weights, options.temperature))
+            .map(|(choice, w)| w * bias_of(choice))
+            .collect();
+        Self::sample_scaled(&choices, &scaled, rng)
+    }
+
+    /// Raises the weights to `1 / temperature`. They are divided by the largest weight first,
+    /// so a low temperature can't overflow them to infinity, the largest always becomes 1.
+    fn tempered(weights: &[i32], temperature: f32) -> Vec<f64> {
+        let exponent = 1.0 / temperature as f64;
+        let max = weights.iter().copied().max().unwrap_or(0).max(1) as f64;
+        weights
+            .iter()
+            .map(|&w| (w as f64 / max).powf(exponent))
+            .collect()
+    }
+
+    /// Draws one of the choices proportionally to its (non-cumulative) floating point weight.
+    fn sample_scaled<R: Rng + ?Sized>(choices: &[T], weights: &[f64], rng: &mut R) -> Option<T> {
+        let total: f64 = weights.iter().sum();
+        if total <= 0.0 || !total.is_finite() {
+            return None;
+        }
+        let mut r: f64 = rng.random_range(0.0..total);
+        for (choice, &weight) in choices.iter().zip(weights) {
+            if r < weight {
+                return Some(choice.clone());
+            }
+            r -= weight;
+        }
+        choices.last().cloned()
+    }
+
+    /// Draws one of the choices from their cumulative distribution.
+    fn sample<R: Rng + ?Sized>(choices: &[T], cumdist: &[i64], rng: &mut R) -> Option<T> {
+        let total = *cumdist.last()?;
+        if total <= 0 {
+            return None;
+        }
+        let r: i64 = rng.random_range(0..total);
+        // the first choice whose cumulative weight is past `r`, so a choice with a weight of 0
+        // (the same cumulative weight as the one before it) is never drawn. `r < total` always
+        // lands inside the distribution, the clamp only guards against a distribution
+        // that doesn't line up with its choices.
+        let idx = cumdist
+            .partition_point(|&c| c <= r)
+            .min(choices.len().checked_sub(1)?);
+        Some(choices[idx].clone())
     }
 
     /// Generates a sequence of words based on the Markov model.
@@ -149,33 +1195,594 @@ where
     /// * `init_state` - An optional initial state to start the generation from.
     /// # Returns
     /// A vector of strings representing the generated sequence of words.
+    /// Generation stops early if it reaches a state with no known transitions.
     pub fn generate(&self, init_state: Option<State<T>>) -> Vec<T> {
+        self.generate_with_rng(init_state, &mut rand::rng())
+    }
+
+    /// Same as `generate`, but draws from the given random number generator,
+    /// which makes the output reproducible when the generator is seeded.
+    pub fn generate_with_rng<R: Rng + ?Sized>(
+        &self,
+        init_state: Option<State<T>>,
+        rng: &mut R,
+    ) -> Vec<T> {
+        self.generate_with_options(init_state, &ChainOptions::default(), rng)
+    }
+
+    /// Generates a sequence of words with the given options.
+    /// # Arguments
+    /// * `init_state` - An optional initial state to start the generation from.
+    /// * `options` - A `ChainOptions` struct containing parameters for generation.
+    /// * `rng` - The random number generator to draw from.
+    /// # Returns
+    /// A vector representing the generated sequence of words.
+    pub fn generate_with_options<R: Rng + ?Sized>(
+        &self,
+        init_state: Option<State<T>>,
+        options: &ChainOptions,
+        rng: &mut R,
+    ) -> Vec<T> {
+        self.iter_with_options(init_state, options.clone(), rng)
+            .collect()
+    }
+
+    /// Returns an iterator that lazily yields the tokens of one generated sequence,
+    /// e.g. for streaming output word by word.
+    /// # Arguments
+    /// * `init_state` - An optional initial state to start the generation from.
+    /// # Returns
+    /// A `Generator` that ends at the end token, a dead end, or the chain's step cap.
+    pub fn iter(&self, init_state: Option<State<T>>) -> Generator<'_, T> {
+        self.iter_with_rng(init_state, rand::rng())
+    }
+
+    /// Same as `iter`, but draws from the given random number generator.
+    pub fn iter_with_rng<R: Rng>(
+        &self,
+        init_state: Option<State<T>>,
+        rng: R,
+    ) -> Generator<'_, T, R> {
+        self.iter_with_options(init_state, ChainOptions::default(), rng)
+    }
+
+    /// Same as `iter`, but with the given options and random number generator.
+    pub fn iter_with_options<R: Rng>(
+        &self,
+        init_state: Option<State<T>>,
+        options: ChainOptions,
+        rng: R,
+    ) -> Generator<'_, T, R> {
+        let limit = options.max_tokens.unwrap_or(usize::MAX).min(self.max_steps);
+        Generator {
+            chain: self,
+            state: init_state.unwrap_or(self.begin_state()),
+            options,
+            rng,
+            remaining: limit,
+            produced: 0,
+        }
+    }
+
+    /// Generates the most likely sequence, always taking the successor with the highest count.
+    /// Ties are broken by picking the smallest token, so the output is deterministic.
+    /// Greedy paths often loop, so the chain's step cap applies as usual.
+    /// # Arguments
+    /// * `init_state` - An optional initial state to start the generation from.
+    /// # Returns
+    /// A vector representing the generated sequence of words.
+    pub fn generate_greedy(&self, init_state: Option<State<T>>) -> Vec<T> {
         let mut state = init_state.unwrap_or(self.begin_state());
         let mut result: Vec<T> = Vec::new();
 
-        loop {
-            let next_word: T = self.next(&state);
-            if next_word == self.token_end {
+        for _ in 0..self.max_steps {
+            let Some((next_word, _)) = self.model.get(&state).and_then(|weights| {
+                weights
+                    .iter()
+                    .max_by(|(a, wa), (b, wb)| wa.cmp(wb).then_with(|| b.cmp(a)))
+            }) else {
+                break;
+            };
+            if *next_word == self.token_end {
                 break;
             }
             result.push(next_word.clone());
             state.remove(0);
+            state.push(next_word.clone());
+        }
+        result
+    }
+
+    /// Computes the log-probability (natural log) of the model producing exactly this sequence,
+    /// starting from the begin state and ending with the end token.
+    /// # Arguments
+    /// * `tokens` - The sequence to score, without begin or end tokens.
+    /// # Returns
+    /// The summed log-probabilities of every transition, or None if any transition is not in the model
+    /// and there is no smoothing, see `with_smoothing`.
+    pub fn sequence_log_prob(&self, tokens: &[T]) -> Option<f64> {
+        let mut state = self.begin_state();
+        let mut log_prob = 0.0;
+
+        for token in tokens.iter().chain(std::iter::once(&self.token_end)) {
+            let weights = self.model.get(&state);
+            let count = weights.and_then(|w| w.get(token)).map_or(0, |&c| c as i64);
+            let total: i64 = weights.map_or(0, |w| w.values().map(|&c| c as i64).sum());
+            let prob = match self.smoothing {
+                Smoothing::None if total == 0 => 0.0,
+                Smoothing::None => count as f64 / total as f64,
+                Smoothing::AddK(k) => {
+                    let possible = self.successor_vocab.max(1) as f64;
+                    (count as f64 + k) / (total as f64 + k * possible)
+                }
+            };
+            if prob.is_nan() || prob <= 0.0 {
+                return None;
+            }
+            log_prob += prob.ln();
+            state.remove(0);
+            state.push(token.clone());
+        }
+
+        Some(log_prob)
+    }
+
+    /// Takes exactly `steps` transitions through the chain, treating it as an endless stream.
+    /// # Arguments
+    /// * `steps` - The number of transitions to take.
+    /// * `init_state` - An optional initial state to start the walk from.
+    /// * `restart_on_end` - What to do when the end token is drawn: if `true`, the end token
+    ///   is not emitted and the walk continues from the begin state, if `false` the walk stops early.
+    ///   Either way, drawing the end token counts as a step. Dead-end states are handled the same way.
+    /// * `rng` - The random number generator to draw from.
+    /// # Returns
+    /// The tokens emitted along the walk, never including the end token.
+    pub fn walk<R: Rng + ?Sized>(
+        &self,
+        steps: usize,
+        init_state: Option<State<T>>,
+        restart_on_end: bool,
+        rng: &mut R,
+    ) -> Vec<T> {
+        let mut state = init_state.unwrap_or(self.begin_state());
+        let mut result: Vec<T> = Vec::with_capacity(steps);
+
+        for _ in 0..steps {
+            let next_word = self.next_with_rng(&state, rng);
+            let Some(next_word) = next_word.filter(|w| *w != self.token_end) else {
+                if !restart_on_end {
+                    break;
+                }
+                state = self.begin_state();
+                continue;
+            };
+            result.push(next_word.clone());
+            state.remove(0);
             state.push(next_word);
         }
         result
     }
 
-    /// Finds an initial state containing the specified start token.
+    /// Picks one of the given states, weighted by its total transition count.
+    /// States that are not part of the model are never picked.
+    fn choose_state<'a, R: Rng + ?Sized>(
+        &self,
+        states: &'a [State<T>],
+        rng: &mut R,
+    ) -> Option<&'a State<T>> {
+        let weights: Vec<i64> = states
+            .iter()
+            .map(|state| {
+                self.model
+                    .get(state)
+                    .map_or(0, |w| w.values().map(|&c| c as i64).sum())
+            })
+            .collect();
+        let total: i64 = weights.iter().sum();
+        if total <= 0 {
+            return None;
+        }
+
+        let mut r = rng.random_range(0..total);
+        for (state, weight) in states.iter().zip(weights) {
+            if r < weight {
+                return Some(state);
+            }
+            r -= weight;
+        }
+        None
+    }
+
+    /// Returns the number of distinct tokens a generation can start with.
+    pub fn begin_branching(&self) -> usize {
+        self.begin_choices.len()
+    }
+
+    /// Picks a random state other than the begin state, weighted by its total transition count,
+    /// so frequent states are picked more often.
+    pub fn random_state<R: Rng + ?Sized>(&self, rng: &mut R) -> Option<State<T>> {
+        let begin_state = self.begin_state();
+        let states: Vec<State<T>> = self
+            .model
+            .keys()
+            .filter(|state| **state != begin_state)
+            .cloned()
+            .collect();
+        self.choose_state(&states, rng).cloned()
+    }
+
+    /// Generates a sequence that begins the way runs of the given category begin.
+    /// # Arguments
+    /// * `category` - A category the chain was trained with, see `new_categorized`.
+    /// # Returns
+    /// The generated sequence, or an empty vector if the category is unknown.
+    pub fn generate_from_category(&self, category: T) -> Vec<T> {
+        self.generate_from_category_with_rng(category, &mut rand::rng())
+    }
+
+    /// Same as `generate_from_category`, but draws from the given random number generator.
+    pub fn generate_from_category_with_rng<R: Rng + ?Sized>(
+        &self,
+        category: T,
+        rng: &mut R,
+    ) -> Vec<T> {
+        let state = vec![category; self.order];
+        if !self.model.contains_key(&state) {
+            return Vec::new();
+        }
+        self.generate_with_rng(Some(state), rng)
+    }
+
+    /// Generates a sequence starting from one of the given candidate states.
+    /// The starting state is sampled proportionally to how often it occurs in the model,
+    /// which gives more natural starts than picking one arbitrarily.
+    /// # Arguments
+    /// * `states` - The candidate initial states, e.g. from `find_init_states`.
+    /// * `rng` - The random number generator to draw from.
+    /// # Returns
+    /// The generated sequence, or an empty vector if none of the states are in the model.
+    pub fn generate_from_states<R: Rng + ?Sized>(
+        &self,
+        states: &[State<T>],
+        rng: &mut R,
+    ) -> Vec<T> {
+        match self.choose_state(states, rng) {
+            Some(state) => self.generate_with_rng(Some(state.clone()), rng),
+            None => Vec::new(),
+        }
+    }
+
+    /// Finds the states generation can continue from right after the specified token,
+    /// i.e. the states ending with it.
     /// # Arguments
     /// * `start` - The token to search for in the initial states.
     /// # Returns
-    /// An optional vector of states containing the start token.
+    /// The states ending with the start token, or None if there are none.
     pub fn find_init_states(&self, start: T) -> Option<Vec<State<T>>> {
-        self.model
+        let mut states: Vec<State<T>> = self
+            .model
             .keys()
-            .filter(|state| state.contains(&start))
+            .filter(|state| state.last() == Some(&start))
             .cloned()
-            .collect::<Vec<State<T>>>()
-            .into()
+            .collect();
+        // sorted so picking one of them with a seeded generator is reproducible.
+        states.sort_unstable();
+        (!states.is_empty()).then_some(states)
+    }
+
+    /// Iterates over every transition in the model, in no particular order.
+    /// # Returns
+    /// One `(state, next token, count)` tuple per transition, the counts add up to
+    /// the number of times each transition was trained.
+    pub fn transitions(&self) -> impl Iterator<Item = (&State<T>, &T, i32)> {
+        self.model.iter().flat_map(|(state, weights)| {
+            weights
+                .iter()
+                .map(move |(next, &count)| (state, next, count))
+        })
+    }
+
+    /// Exports the model as a flat edge list.
+    /// # Returns
+    /// One `(state, next token, weight)` tuple per transition in the model.
+    pub fn to_edge_list(&self) -> Vec<(State<T>, T, i32)> {
+        self.transitions()
+            .map(|(state, next, weight)| (state.clone(), next.clone(), weight))
+            .collect()
+    }
+
+    /// Exports the model as a Graphviz `digraph`, e.g. for rendering with `dot -Tsvg`.
+    /// Nodes are states and each edge leads to the state a transition moves to,
+    /// labeled with the token taken, its count and its probability.
+    /// # Arguments
+    /// * `top_k` - If set, only the `k` most likely transitions of each state are drawn,
+    ///   which keeps large models readable.
+    /// # Returns
+    /// The graph in the DOT language.
+    pub fn to_dot(&self, top_k: Option<usize>) -> String {
+        let quote = |value: &dyn std::fmt::Debug| {
+            format!("{value:?}")
+                .replace('\\', "\\\\")
+                .replace('"', "\\\"")
+        };
+
+        let mut states: Vec<&State<T>> = self.model.keys().collect();
+        states.sort_unstable();
+
+        let mut dot = String::from("digraph {\n");
+        for state in &states {
+            dot.push_str(&format!("    \"{}\";\n", quote(state)));
+        }
+        for state in states {
+            let weights = &self.model[state];
+            let total: i64 = weights.values().map(|&w| w as i64).sum();
+            let mut edges: Vec<(&T, i32)> = weights.iter().map(|(t, &w)| (t, w)).collect();
+            edges.sort_unstable_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(b.0)));
+            edges.truncate(top_k.unwrap_or(usize::MAX));
+
+            for (next, count) in edges {
+                let mut to = state.clone();
+                if !to.is_empty() {
+                    to.remove(0);
+                }
+                to.push(next.clone());
+                dot.push_str(&format!(
+                    "    \"{}\" -> \"{}\" [label=\"{} {} ({:.2})\"];\n",
+                    quote(state),
+                    quote(&to),
+                    quote(next),
+                    count,
+                    count as f64 / total as f64,
+                ));
+            }
+        }
+        dot.push_str("}\n");
+        dot
+    }
+}
+
+impl<T> Chain<Token<T>>
+where
+    T: Eq + Hash + Ord + Clone + std::fmt::Debug,
+{
+    /// Creates a new Chain from runs of plain values, padded with `Token::Begin` and `Token::End`.
+    /// Unlike `new`, no value has to be set aside as a marker, any value can appear in a run.
+    /// # Arguments
+    /// * `data` - The sequences to train on.
+    /// * `order` - The number of tokens in a state.
+    /// # Returns
+    /// A new instance of `Chain`.
+    pub fn from_words(data: &[Vec<T>], order: usize) -> Self {
+        let runs = data
+            .iter()
+            .map(|run| run.iter().cloned().map(Token::Word).collect());
+        Self::from_runs(runs, Token::Begin, Token::End, order)
+    }
+
+    /// Generates a sequence from the begin state and unwraps its values.
+    pub fn generate_words(&self) -> Vec<T> {
+        self.generate_words_with_rng(&mut rand::rng())
+    }
+
+    /// Same as `generate_words`, but draws from the given random number generator.
+    pub fn generate_words_with_rng<R: Rng + ?Sized>(&self, rng: &mut R) -> Vec<T> {
+        self.generate_with_rng(None, rng)
+            .into_iter()
+            .filter_map(Token::into_word)
+            .collect()
+    }
+}
+
+#[cfg(feature = "rayon")]
+impl<T> Chain<T>
+where
+    T: Eq + Hash + Ord + Clone + std::fmt::Debug + Send + Sync,
+{
+    /// Same as `new`, but builds the model on multiple threads.
+    /// Each thread builds a partial model from its share of the runs, which are then summed,
+    /// so the result is identical to the sequential build.
+    pub fn new_parallel(data: &[Vec<T>], begin: T, end: T, order: usize) -> Self {
+        use rayon::prelude::*;
+
+        let mut chain = Self::default(begin, end, order);
+        chain.model = data
+            .par_iter()
+            .fold(Model::new, |mut model, run| {
+                chain.insert_run(&mut model, run, 1);
+                model
+            })
+            .reduce(Model::new, |mut a, b| {
+                Self::merge_models(&mut a, &b, 1.0);
+                a
+            });
+        chain.compute();
+        chain
+    }
+}
+
+/// Borrowed view of a chain, used for serialization.
+/// The model is written as a list of pairs, since formats like JSON don't allow sequences as map keys.
+/// States and successors are written in sorted order, so the same model always gives the same output.
+#[cfg(feature = "serde")]
+#[derive(serde::Serialize)]
+struct ChainRef<'a, T> {
+    token_begin: &'a T,
+    token_end: &'a T,
+    order: usize,
+    max_steps: usize,
+    precomputed: bool,
+    model: SortedModel<'a, T>,
+    begin_strategy: &'a BeginStrategy<T>,
+    min_begin_weight: i32,
+    smoothing: Smoothing,
+}
+
+/// Borrowed view of a model with its states and successors in sorted order.
+#[cfg(feature = "serde")]
+type SortedModel<'a, T> = Vec<(&'a State<T>, std::collections::BTreeMap<&'a T, &'a i32>)>;
+
+#[cfg(feature = "serde")]
+fn sorted_model<T: Ord>(model: &Model<T>) -> SortedModel<'_, T> {
+    let mut sorted: SortedModel<'_, T> = model
+        .iter()
+        .map(|(state, weights)| (state, weights.iter().collect()))
+        .collect();
+    sorted.sort_unstable_by(|a, b| a.0.cmp(b.0));
+    sorted
+}
+
+/// Owned counterpart of `ChainRef`, used for deserialization.
+#[cfg(feature = "serde")]
+#[derive(serde::Deserialize)]
+struct ChainData<T>
+where
+    T: Eq + Hash,
+{
+    token_begin: T,
+    token_end: T,
+    order: usize,
+    max_steps: usize,
+    #[serde(default)]
+    precomputed: bool,
+    model: Vec<(State<T>, Weight<T>)>,
+    #[serde(default = "BeginStrategy::default")]
+    begin_strategy: BeginStrategy<T>,
+    #[serde(default)]
+    min_begin_weight: i32,
+    #[serde(default)]
+    smoothing: Smoothing,
+}
+
+#[cfg(feature = "serde")]
+impl<T> serde::Serialize for Chain<T>
+where
+    T: Eq + Hash + Ord + Clone + std::fmt::Debug + serde::Serialize,
+{
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        ChainRef {
+            token_begin: &self.token_begin,
+            token_end: &self.token_end,
+            order: self.order,
+            max_steps: self.max_steps,
+            precomputed: self.precomputed.is_some(),
+            model: sorted_model(&self.model),
+            begin_strategy: &self.begin_strategy,
+            min_begin_weight: self.min_begin_weight,
+            smoothing: self.smoothing,
+        }
+        .serialize(serializer)
+    }
+}
+
+/// The begin caches and precomputed distributions are not stored,
+/// they are recomputed from the model after loading.
+#[cfg(feature = "serde")]
+impl<'de, T> serde::Deserialize<'de> for Chain<T>
+where
+    T: Eq + Hash + Ord + Clone + std::fmt::Debug + serde::Deserialize<'de>,
+{
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let data = ChainData::<T>::deserialize(deserializer)?;
+        let mut chain = Self::default(data.token_begin, data.token_end, data.order);
+        chain.max_steps = data.max_steps;
+        chain.model = data.model.into_iter().collect();
+        chain.begin_strategy = data.begin_strategy;
+        chain.min_begin_weight = data.min_begin_weight;
+        chain.smoothing = data.smoothing;
+        if data.precomputed {
+            chain.precomputed = Some(HashMap::new());
+        }
+        chain.compute();
+        Ok(chain)
+    }
+}
+
+#[cfg(feature = "serde")]
+impl Chain<String> {
+    /// Creates a chain from a model exported by Python's markovify, i.e. the output of
+    /// `Chain.to_json()` or `Text.to_json()`. Both the plain list of `[state, {word: count}]`
+    /// pairs and the string-encoded forms markovify nests it in are accepted.
+    /// # Arguments
+    /// * `json` - The markovify JSON.
+    /// # Returns
+    /// A new instance of `Chain`, or the error if the input is not a markovify model.
+    pub fn from_markovify_json(json: &str) -> Result<Self, serde_json::Error> {
+        use serde::de::Error;
+        use serde_json::Value;
+
+        let mut value: Value = serde_json::from_str(json)?;
+        let value = loop {
+            value = match value {
+                Value::String(inner) => serde_json::from_str(&inner)?,
+                Value::Object(mut map) => map
+                    .remove("chain")
+                    .ok_or_else(|| serde_json::Error::custom("missing field `chain`"))?,
+                value => break value,
+            };
+        };
+
+        let model: Vec<(State<String>, Weight<String>)> = serde_json::from_value(value)?;
+        let order = model.first().map_or(STATE_SIZE, |(state, _)| state.len());
+        if model.iter().any(|(state, _)| state.len() != order) {
+            return Err(serde_json::Error::custom("states of different sizes"));
+        }
+
+        if model
+            .iter()
+            .any(|(_, weights)| weights.values().any(|&count| count <= 0))
+        {
+            return Err(serde_json::Error::custom("counts must be positive"));
+        }
+
+        let mut chain = Self::default(String::from("___BEGIN__"), String::from("___END__"), order);
+        chain.model = model.into_iter().collect();
+        chain.compute();
+        Ok(chain)
+    }
+
+    /// Exports the model in the format of Python markovify's `Chain.to_json()`,
+    /// which `markovify.Chain.from_json` can load.
+    /// markovify expects the begin and end tokens to be `"___BEGIN__"` and `"___END__"`.
+    pub fn to_markovify_json(&self) -> String {
+        // string keys and integer counts always serialize.
+        serde_json::to_string(&sorted_model(&self.model)).unwrap()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::SeedableRng;
+    use rand::rngs::StdRng;
+
+    #[test]
+    fn sample_never_draws_a_zero_weight_choice() {
+        // b has a weight of 0, so a draw of exactly 1 must go to c.
+        let choices = ['a', 'b', 'c', 'd'];
+        let cumdist = [1, 1, 2, 3];
+        let mut rng = StdRng::seed_from_u64(0);
+        let mut drawn = HashSet::new();
+        for _ in 0..200 {
+            drawn.insert(Chain::<char>::sample(&choices, &cumdist, &mut rng).unwrap());
+        }
+        assert_eq!(drawn, HashSet::from(['a', 'c', 'd']));
+    }
+    #[test]
+    fn accumulate_of_nothing_is_empty() {
+        assert!(Chain::<char>::accumulate(&[]).is_empty());
+        assert_eq!(Chain::<char>::accumulate(&[2, 0, 3]), vec![2, 2, 5]);
+    }
+
+    #[test]
+    fn next_stops_at_a_state_with_no_successors() {
+        let mut chain = Chain::new(&[vec!['a', 'b']], '^', '$', 1);
+        chain.model_mut().insert(vec!['c'], Weight::new());
+        let mut rng = StdRng::seed_from_u64(0);
+        assert_eq!(chain.next_with_rng(&vec!['c'], &mut rng), None);
+        assert!(
+            chain
+                .generate_with_rng(Some(vec!['c']), &mut rng)
+                .is_empty()
+        );
     }
 }
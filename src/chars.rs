@@ -0,0 +1,115 @@
+use std::collections::HashSet;
+
+use crate::chain::{Chain, ChainOptions, STATE_SIZE};
+use crate::error::GenerateError;
+
+use rand::Rng;
+
+const BEGIN: char = '\u{2}'; // start of text
+const END: char = '\u{3}'; // end of text
+
+/// Options for generating words with `CharText`.
+#[derive(Debug, Clone)]
+pub struct CharOptions {
+    pub tries: i32,
+    pub min_chars: usize,
+    pub max_chars: usize,
+    /// Whether words that appear verbatim in the training data may be returned.
+    pub allow_copies: bool,
+}
+
+impl Default for CharOptions {
+    fn default() -> Self {
+        Self {
+            tries: 999,
+            min_chars: 1,
+            max_chars: 20,
+            allow_copies: false,
+        }
+    }
+}
+
+/// CharText generates new words from a character-level Markov model,
+/// e.g. names or gibberish that sound like the training data.
+#[derive(Debug)]
+pub struct CharText {
+    words: HashSet<String>,
+    chain: Chain<char>,
+}
+
+impl CharText {
+    /// Creates a new CharText instance from the given data.
+    /// # Arguments
+    /// * `data` - A string with one word per line, e.g. a list of names.
+    /// # Returns
+    /// A new instance of `CharText`.
+    pub fn new(data: String) -> Self {
+        Self::with_order(data, STATE_SIZE)
+    }
+
+    /// Creates a new CharText instance whose chain uses states of `order` characters.
+    /// # Arguments
+    /// * `data` - A string with one word per line, e.g. a list of names.
+    ///   A word listed more than once is trained on every time, so it weighs more.
+    /// * `order` - The number of characters in a state.
+    /// # Returns
+    /// A new instance of `CharText`.
+    pub fn with_order(data: String, order: usize) -> Self {
+        let lines: Vec<String> = data
+            .lines()
+            .map(|line| line.trim().replace([BEGIN, END], ""))
+            .filter(|line| !line.is_empty())
+            .collect();
+        let runs: Vec<Vec<char>> = lines.iter().map(|w| w.chars().collect()).collect();
+
+        Self {
+            chain: Chain::new(&runs, BEGIN, END, order),
+            words: lines.into_iter().collect(),
+        }
+    }
+
+    /// Generates a new word based on the Markov model and the provided options.
+    /// # Arguments
+    /// * `options` - A `CharOptions` struct containing parameters for generation.
+    /// # Returns
+    /// The generated word, or a `GenerateError` describing why nothing could be generated.
+    pub fn generate(&self, options: CharOptions) -> Result<String, GenerateError> {
+        self.generate_with_rng(options, &mut rand::rng())
+    }
+
+    /// Same as `generate`, but draws from the given random number generator.
+    pub fn generate_with_rng<R: Rng + ?Sized>(
+        &self,
+        options: CharOptions,
+        rng: &mut R,
+    ) -> Result<String, GenerateError> {
+        if self.chain.is_empty() {
+            return Err(GenerateError::EmptyModel);
+        }
+        if options.max_chars == 0 || options.min_chars > options.max_chars {
+            return Err(GenerateError::ConstraintsUnsatisfiable);
+        }
+
+        let chain_options = ChainOptions {
+            max_tokens: Some(options.max_chars.saturating_add(1)),
+            ..ChainOptions::default()
+        };
+        for _ in 0..options.tries {
+            let chars = self.chain.generate_with_options(None, &chain_options, rng);
+            if chars.len() < options.min_chars || chars.len() > options.max_chars {
+                continue;
+            }
+            let word: String = chars.into_iter().collect();
+            if options.allow_copies || !self.words.contains(&word) {
+                return Ok(word);
+            }
+        }
+
+        Err(GenerateError::ExhaustedTries)
+    }
+
+    /// Returns the underlying chain of characters.
+    pub fn chain(&self) -> &Chain<char> {
+        &self.chain
+    }
+}
@@ -0,0 +1,133 @@
+use std::fmt;
+
+/// Errors that can occur while generating text.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum GenerateError {
+    /// The model has no data to generate from.
+    EmptyModel,
+    /// The options can never be satisfied, e.g. `min_words` is larger than `max_words`.
+    ConstraintsUnsatisfiable,
+    /// The starting words don't lead to any state of the model.
+    UnknownStart,
+    /// No candidate passed the length and overlap checks within the allowed tries.
+    ExhaustedTries,
+    /// No candidate passed the length and overlap checks within the allowed time.
+    Timeout,
+}
+
+impl fmt::Display for GenerateError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::EmptyModel => write!(f, "the model is empty"),
+            Self::ConstraintsUnsatisfiable => write!(f, "the options can never be satisfied"),
+            Self::UnknownStart => write!(f, "the starting words are not in the model"),
+            Self::ExhaustedTries => write!(f, "no valid output within the allowed tries"),
+            Self::Timeout => write!(f, "no valid output within the allowed time"),
+        }
+    }
+}
+
+impl std::error::Error for GenerateError {}
+
+/// Errors that can occur while combining chains with `Chain::combine`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum CombineError {
+    /// No chains were given.
+    Empty,
+    /// The number of weights doesn't match the number of chains.
+    WeightCount,
+    /// The chains don't share the same begin and end tokens.
+    TokenMismatch,
+    /// The chains don't share the same order.
+    OrderMismatch,
+}
+
+impl fmt::Display for CombineError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Empty => write!(f, "no chains to combine"),
+            Self::WeightCount => write!(f, "expected one weight per chain"),
+            Self::TokenMismatch => write!(f, "chains have different begin or end tokens"),
+            Self::OrderMismatch => write!(f, "chains have different orders"),
+        }
+    }
+}
+
+impl std::error::Error for CombineError {}
+
+/// Any error the crate can return, for callers that want to handle them in one place.
+#[derive(Debug)]
+pub enum MarukovError {
+    /// Generating text failed, see `GenerateError`.
+    Generate(GenerateError),
+    /// Combining chains failed, see `CombineError`.
+    Combine(CombineError),
+    /// A pattern for rejecting input lines is not a valid regular expression.
+    #[cfg(feature = "text")]
+    InvalidPattern(regex::Error),
+    /// Reading or writing JSON failed.
+    #[cfg(feature = "serde")]
+    Json(serde_json::Error),
+    /// Reading or writing a file failed.
+    Io(std::io::Error),
+}
+
+impl fmt::Display for MarukovError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Generate(e) => write!(f, "generation failed: {e}"),
+            Self::Combine(e) => write!(f, "combining failed: {e}"),
+            #[cfg(feature = "text")]
+            Self::InvalidPattern(e) => write!(f, "invalid pattern: {e}"),
+            #[cfg(feature = "serde")]
+            Self::Json(e) => write!(f, "invalid json: {e}"),
+            Self::Io(e) => write!(f, "io error: {e}"),
+        }
+    }
+}
+
+impl std::error::Error for MarukovError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Self::Generate(e) => Some(e),
+            Self::Combine(e) => Some(e),
+            #[cfg(feature = "text")]
+            Self::InvalidPattern(e) => Some(e),
+            #[cfg(feature = "serde")]
+            Self::Json(e) => Some(e),
+            Self::Io(e) => Some(e),
+        }
+    }
+}
+
+impl From<GenerateError> for MarukovError {
+    fn from(e: GenerateError) -> Self {
+        Self::Generate(e)
+    }
+}
+
+impl From<CombineError> for MarukovError {
+    fn from(e: CombineError) -> Self {
+        Self::Combine(e)
+    }
+}
+
+#[cfg(feature = "text")]
+impl From<regex::Error> for MarukovError {
+    fn from(e: regex::Error) -> Self {
+        Self::InvalidPattern(e)
+    }
+}
+
+#[cfg(feature = "serde")]
+impl From<serde_json::Error> for MarukovError {
+    fn from(e: serde_json::Error) -> Self {
+        Self::Json(e)
+    }
+}
+
+impl From<std::io::Error> for MarukovError {
+    fn from(e: std::io::Error) -> Self {
+        Self::Io(e)
+    }
+}
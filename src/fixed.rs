@@ -0,0 +1,143 @@
+use std::collections::HashMap;
+use std::hash::Hash;
+
+use rand::Rng;
+
+use crate::chain::{MAX_STEPS, STATE_SIZE};
+
+/// FixedChain is a `Chain` whose order is fixed at compile time.
+///
+/// States are stored as `[T; N]` instead of `Vec<T>`, and the distributions of every state are
+/// compiled once up front, so generating walks the model without any per-step allocation.
+/// It only covers training and generation, use `Chain` for everything else.
+#[derive(Debug)]
+pub struct FixedChain<T, const N: usize>
+where
+    T: Eq + Hash + Ord + Clone + std::fmt::Debug,
+{
+    token_begin: T,
+    token_end: T,
+    model: HashMap<[T; N], (Vec<T>, Vec<i64>)>,
+    max_steps: usize,
+}
+
+/// A FixedChain with the default order, matching `Chain::new(.., STATE_SIZE)`.
+pub type DefaultFixedChain<T> = FixedChain<T, STATE_SIZE>;
+
+impl<T, const N: usize> FixedChain<T, N>
+where
+    T: Eq + Hash + Ord + Clone + std::fmt::Debug,
+{
+    /// Creates a new FixedChain from the given data.
+    /// # Arguments
+    /// * `data` - A slice of runs, where each run is a sequence of tokens.
+    /// * `begin` - The token used to pad the start of each run.
+    /// * `end` - The token used to mark the end of each run.
+    /// # Returns
+    /// A new instance of `FixedChain`.
+    pub fn new(data: &[Vec<T>], begin: T, end: T) -> Self {
+        let mut counts: HashMap<[T; N], HashMap<T, i32>> = HashMap::new();
+        for run in data {
+            let mut items: Vec<&T> = vec![&begin; N];
+            items.extend(run);
+            items.push(&end);
+
+            for window in items.windows(N + 1) {
+                let state: [T; N] = std::array::from_fn(|i| window[i].clone());
+                *counts
+                    .entry(state)
+                    .or_default()
+                    .entry(window[N].clone())
+                    .or_insert(0) += 1;
+            }
+        }
+
+        let model = counts
+            .into_iter()
+            .map(|(state, weights)| {
+                // sorted by token like `Chain`, so seeded generation is reproducible.
+                let mut weights: Vec<(T, i32)> = weights.into_iter().collect();
+                weights.sort_unstable_by(|a, b| a.0.cmp(&b.0));
+                let mut total: i64 = 0;
+                let (choices, cumdist) = weights
+                    .into_iter()
+                    .map(|(token, count)| {
+                        total += count as i64;
+                        (token, total)
+                    })
+                    .unzip();
+                (state, (choices, cumdist))
+            })
+            .collect();
+
+        Self {
+            token_begin: begin,
+            token_end: end,
+            model,
+            max_steps: MAX_STEPS,
+        }
+    }
+
+    /// Sets the maximum number of steps a single `generate` call may take, see `Chain::with_max_steps`.
+    pub fn with_max_steps(mut self, max_steps: usize) -> Self {
+        self.max_steps = max_steps;
+        self
+    }
+
+    /// Returns the number of states in the model.
+    pub fn state_count(&self) -> usize {
+        self.model.len()
+    }
+
+    /// Returns the state generation starts from, `N` begin tokens.
+    pub fn begin_state(&self) -> [T; N] {
+        std::array::from_fn(|_| self.token_begin.clone())
+    }
+
+    /// Moves to the next state based on the current state, see `Chain::next_with_rng`.
+    pub fn next_with_rng<R: Rng + ?Sized>(&self, state: &[T; N], rng: &mut R) -> Option<T> {
+        let (choices, cumdist) = self.model.get(state)?;
+        let total = *cumdist.last()?;
+        if total <= 0 {
+            return None;
+        }
+        let r: i64 = rng.random_range(0..total);
+        let idx = cumdist.partition_point(|&c| c <= r).min(choices.len() - 1);
+        Some(choices[idx].clone())
+    }
+
+    /// Generates a sequence of tokens based on the Markov model.
+    /// # Arguments
+    /// * `init_state` - An optional initial state to start the generation from.
+    /// # Returns
+    /// The generated tokens, stopping at the end token or a state with no known transitions.
+    pub fn generate(&self, init_state: Option<[T; N]>) -> Vec<T> {
+        self.generate_with_rng(init_state, &mut rand::rng())
+    }
+
+    /// Same as `generate`, but draws from the given random number generator.
+    pub fn generate_with_rng<R: Rng + ?Sized>(
+        &self,
+        init_state: Option<[T; N]>,
+        rng: &mut R,
+    ) -> Vec<T> {
+        let mut state = init_state.unwrap_or_else(|| self.begin_state());
+        let mut generated = Vec::new();
+
+        for _ in 0..self.max_steps {
+            let Some(next_word) = self.next_with_rng(&state, rng) else {
+                break;
+            };
+            if next_word == self.token_end {
+                break;
+            }
+            if N > 0 {
+                state.rotate_left(1);
+                state[N - 1] = next_word.clone();
+            }
+            generated.push(next_word);
+        }
+
+        generated
+    }
+}
@@ -0,0 +1,137 @@
+use std::collections::HashMap;
+use std::hash::Hash;
+use std::sync::Arc;
+
+use crate::chain::{Chain, State};
+
+/// Interner maps values to compact `u32` ids, storing each distinct value only once.
+/// The lookup table and the id table share the value through an `Arc`, like `Vocab` does,
+/// instead of each holding its own clone.
+#[derive(Debug)]
+pub struct Interner<T>
+where
+    T: Eq + Hash + Clone + std::fmt::Debug,
+{
+    ids: HashMap<Arc<T>, u32>,
+    values: Vec<Arc<T>>,
+}
+
+impl<T> Default for Interner<T>
+where
+    T: Eq + Hash + Clone + std::fmt::Debug,
+{
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T> Interner<T>
+where
+    T: Eq + Hash + Clone + std::fmt::Debug,
+{
+    /// Creates a new, empty interner.
+    pub fn new() -> Self {
+        Self {
+            ids: HashMap::new(),
+            values: Vec::new(),
+        }
+    }
+
+    /// Returns the id of a value, adding it to the table if it is not present yet.
+    pub fn intern(&mut self, value: &T) -> u32 {
+        if let Some(&id) = self.ids.get(value) {
+            return id;
+        }
+
+        let id = self.values.len() as u32;
+        let value = Arc::new(value.clone());
+        self.values.push(Arc::clone(&value));
+        self.ids.insert(value, id);
+
+        id
+    }
+
+    /// Returns the id of a value, or None if it was never interned.
+    pub fn get(&self, value: &T) -> Option<u32> {
+        self.ids.get(value).cloned()
+    }
+
+    /// Returns the value behind an id, or None if the id is unknown.
+    pub fn resolve(&self, id: u32) -> Option<&T> {
+        self.values.get(id as usize).map(|value| value.as_ref())
+    }
+}
+
+/// InternedChain is a `Chain` whose states are stored as interned ids instead of clones of `T`.
+///
+/// A plain `Chain<T>` clones every token into each state key it appears in, which for
+/// `T = String` means the same word is allocated once per state. Here each distinct token
+/// is stored once in an `Interner`, and the model itself only holds `u32` ids,
+/// so memory use grows with the number of states rather than with the size of the tokens.
+#[derive(Debug)]
+pub struct InternedChain<T>
+where
+    T: Eq + Hash + Clone + std::fmt::Debug,
+{
+    interner: Interner<T>,
+    chain: Chain<u32>,
+}
+
+impl<T> InternedChain<T>
+where
+    T: Eq + Hash + Clone + std::fmt::Debug,
+{
+    /// Creates a new InternedChain from the given data.
+    /// # Arguments
+    /// * `data` - A slice of runs, where each run is a sequence of tokens.
+    /// * `begin` - The token used to pad the start of each run.
+    /// * `end` - The token used to mark the end of each run.
+    /// * `order` - The number of tokens in a state.
+    /// # Returns
+    /// A new instance of `InternedChain`.
+    pub fn new(data: &[Vec<T>], begin: T, end: T, order: usize) -> Self {
+        let mut interner = Interner::new();
+        let begin = interner.intern(&begin);
+        let end = interner.intern(&end);
+        let runs: Vec<Vec<u32>> = data
+            .iter()
+            .map(|run| run.iter().map(|t| interner.intern(t)).collect())
+            .collect();
+
+        Self {
+            chain: Chain::new(&runs, begin, end, order),
+            interner,
+        }
+    }
+
+    /// Generates a sequence of tokens based on the Markov model.
+    /// # Arguments
+    /// * `init_state` - An optional initial state to start the generation from.
+    /// # Returns
+    /// The generated tokens, or an empty vector if the initial state contains unknown tokens.
+    pub fn generate(&self, init_state: Option<State<T>>) -> Vec<T> {
+        let init_state = match init_state {
+            Some(state) => match state.iter().map(|t| self.interner.get(t)).collect() {
+                Some(state) => Some(state),
+                None => return Vec::new(),
+            },
+            None => None,
+        };
+
+        self.chain
+            .generate(init_state)
+            .into_iter()
+            .filter_map(|id| self.interner.resolve(id).cloned())
+            .collect()
+    }
+
+    /// Returns the underlying chain of interned ids.
+    pub fn chain(&self) -> &Chain<u32> {
+        &self.chain
+    }
+
+    /// Returns the table mapping tokens to interned ids.
+    pub fn interner(&self) -> &Interner<T> {
+        &self.interner
+    }
+}
@@ -1,7 +1,31 @@
+#[cfg(feature = "text")]
+pub mod builder;
 pub mod chain;
+pub mod chars;
+pub mod error;
+pub mod fixed;
+pub mod interned;
+#[cfg(feature = "text")]
+pub mod splitter;
+#[cfg(feature = "text")]
+mod suffix;
+#[cfg(feature = "text")]
 pub mod text;
+pub mod tokenizer;
 pub mod vocab;
 
-pub use chain::Chain;
-pub use text::{Text, TextOptions};
-pub use vocab::Vocab;
+#[cfg(feature = "text")]
+pub use builder::TextBuilder;
+pub use chain::{
+    BeginStrategy, Chain, ChainOptions, Generator, SamplingStrategy, Smoothing, Token,
+};
+pub use chars::{CharOptions, CharText};
+pub use error::{CombineError, GenerateError, MarukovError};
+pub use fixed::{DefaultFixedChain, FixedChain};
+pub use interned::{InternedChain, Interner};
+#[cfg(feature = "text")]
+pub use splitter::SentenceSplitter;
+#[cfg(feature = "text")]
+pub use text::{Explanation, Text, TextOptions};
+pub use tokenizer::Tokenizer;
+pub use vocab::{Normalization, Vocab};
@@ -0,0 +1,100 @@
+/// Words ending in a period that usually don't end a sentence.
+const ABBREVIATIONS: &[&str] = &[
+    "mr", "mrs", "ms", "dr", "prof", "sr", "jr", "st", "mt", "vs", "etc", "e.g", "i.e", "no",
+    "vol", "fig", "approx", "jan", "feb", "mar", "apr", "jun", "jul", "aug", "sep", "sept", "oct",
+    "nov", "dec",
+];
+
+/// Closing characters that may follow the sentence-ending punctuation, e.g. `end.)`.
+const CLOSING: &[char] = &['"', '\'', ')', ']', '}'];
+
+/// SentenceSplitter decides how the input text is split into sentences.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum SentenceSplitter {
+    /// Every line is a sentence, both `\n` and `\r\n` line endings are recognized.
+    #[default]
+    Newline,
+    /// Sentences end with `.`, `!` or `?` followed by whitespace, line breaks are treated as spaces.
+    /// Common abbreviations (`Mr.`, `e.g.`), initials (`J.`) and periods followed by
+    /// a lowercase word don't end a sentence.
+    Punctuation,
+    /// Paragraphs separated by blank lines are sentences, the lines within a paragraph are
+    /// soft wraps and joined with spaces. Lines with only whitespace count as blank.
+    Paragraph,
+}
+
+impl SentenceSplitter {
+    /// Splits the data into sentences.
+    pub fn split<'a>(&self, data: &'a str) -> Vec<&'a str> {
+        match self {
+            Self::Newline => data.lines().collect(),
+            Self::Punctuation => Self::split_punctuation(data),
+            Self::Paragraph => Self::split_paragraphs(data),
+        }
+    }
+
+    /// Splits on blank lines, each paragraph is returned with its inner line breaks,
+    /// which become spaces once it is split into words.
+    fn split_paragraphs(data: &str) -> Vec<&str> {
+        let base = data.as_ptr() as usize;
+        let mut paragraphs: Vec<&str> = Vec::new();
+        let mut span: Option<(usize, usize)> = None;
+        for line in data.lines() {
+            if line.trim().is_empty() {
+                paragraphs.extend(span.take().map(|(begin, end)| &data[begin..end]));
+                continue;
+            }
+            let begin = line.as_ptr() as usize - base;
+            let end = begin + line.len();
+            span = Some((span.map_or(begin, |(begin, _)| begin), end));
+        }
+        paragraphs.extend(span.map(|(begin, end)| &data[begin..end]));
+
+        paragraphs
+    }
+
+    /// Splits on sentence-ending punctuation.
+    fn split_punctuation(data: &str) -> Vec<&str> {
+        let base = data.as_ptr() as usize;
+        let words: Vec<(usize, &str)> = data
+            .split_whitespace()
+            .map(|word| (word.as_ptr() as usize - base, word))
+            .collect();
+
+        let mut sentences: Vec<&str> = Vec::new();
+        let mut begin: Option<usize> = None;
+        for (i, &(offset, word)) in words.iter().enumerate() {
+            let begin_at = *begin.get_or_insert(offset);
+            let next = words.get(i + 1).map(|&(_, w)| w);
+            if Self::ends_sentence(word, next) {
+                sentences.push(&data[begin_at..offset + word.len()]);
+                begin = None;
+            }
+        }
+
+        sentences
+    }
+
+    /// Checks whether `word` ends a sentence, given the word after it.
+    fn ends_sentence(word: &str, next: Option<&str>) -> bool {
+        let trimmed = word.trim_end_matches(CLOSING);
+        let Some(last) = trimmed.chars().last() else {
+            return false;
+        };
+
+        match last {
+            '!' | '?' => true,
+            '.' => {
+                let Some(next) = next else {
+                    return true;
+                };
+                let stem = trimmed.trim_end_matches('.').to_lowercase();
+                let is_initial = stem.chars().count() == 1 && stem.chars().all(char::is_alphabetic);
+                let continues = next.chars().next().is_some_and(char::is_lowercase);
+                !(is_initial || continues || ABBREVIATIONS.contains(&stem.as_str()))
+            }
+            _ => next.is_none(),
+        }
+    }
+}
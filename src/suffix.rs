@@ -0,0 +1,213 @@
+/// Marks a state without a suffix link, only the initial state has none,
+/// and the end of a state's list of transitions.
+const NONE: u32 = u32::MAX;
+
+#[derive(Debug, Clone, Copy)]
+struct Node {
+    /// Length of the longest substring ending in this state.
+    len: u32,
+    link: u32,
+    /// Index of the first of this state's transitions in `SuffixAutomaton::edges`.
+    first: u32,
+}
+
+/// A transition by one byte, linked to the next transition of the same state.
+#[derive(Debug, Clone, Copy)]
+struct Edge {
+    byte: u8,
+    to: u32,
+    next: u32,
+}
+
+/// SuffixAutomaton recognizes every substring of the text it was built from.
+///
+/// Checking whether a string occurs in the text takes time proportional to the string,
+/// instead of the text like `str::contains`, and gives the same answer, as both compare bytes.
+/// Text can be appended at any time, the automaton is extended in place.
+///
+/// The states and transitions live in two flat tables, so the automaton costs two allocations
+/// however large the text is. There are at most `2 * len` states and `3 * len` transitions.
+#[derive(Debug, Clone)]
+pub(crate) struct SuffixAutomaton {
+    nodes: Vec<Node>,
+    edges: Vec<Edge>,
+    last: u32,
+}
+
+impl SuffixAutomaton {
+    /// Builds the automaton of the given text.
+    pub(crate) fn new(text: &str) -> Self {
+        let mut automaton = Self {
+            nodes: Vec::with_capacity(text.len() * 2 + 1),
+            edges: Vec::with_capacity(text.len() * 2),
+            last: 0,
+        };
+        automaton.nodes.push(Node {
+            len: 0,
+            link: NONE,
+            first: NONE,
+        });
+        automaton.extend(text);
+        automaton
+    }
+
+    /// Appends text to the end of what the automaton recognizes.
+    pub(crate) fn extend(&mut self, text: &str) {
+        for &byte in text.as_bytes() {
+            self.push(byte);
+        }
+    }
+
+    /// Checks whether `needle` occurs in the text, the same as `text.contains(needle)`.
+    pub(crate) fn contains(&self, needle: &str) -> bool {
+        let mut state = 0;
+        for &byte in needle.as_bytes() {
+            match self.get(state, byte) {
+                Some(next) => state = next,
+                None => return false,
+            }
+        }
+        true
+    }
+
+    /// Returns the index of a state's transition by `byte`, if it has one.
+    fn edge(&self, state: u32, byte: u8) -> Option<usize> {
+        let mut edge = self.nodes[state as usize].first;
+        while edge != NONE {
+            if self.edges[edge as usize].byte == byte {
+                return Some(edge as usize);
+            }
+            edge = self.edges[edge as usize].next;
+        }
+        None
+    }
+
+    fn get(&self, state: u32, byte: u8) -> Option<u32> {
+        self.edge(state, byte).map(|edge| self.edges[edge].to)
+    }
+
+    fn set(&mut self, state: u32, byte: u8, to: u32) {
+        match self.edge(state, byte) {
+            Some(edge) => self.edges[edge].to = to,
+            None => {
+                let node = &mut self.nodes[state as usize];
+                self.edges.push(Edge {
+                    byte,
+                    to,
+                    next: node.first,
+                });
+                node.first = self.edges.len() as u32 - 1;
+            }
+        }
+    }
+
+    /// Adds a state with the same transitions as `state`, but of length `len`.
+    fn clone_node(&mut self, state: u32, len: u32) -> u32 {
+        let clone = self.nodes.len() as u32;
+        let node = self.nodes[state as usize];
+        self.nodes.push(Node {
+            len,
+            link: node.link,
+            first: NONE,
+        });
+        let mut edge = node.first;
+        while edge != NONE {
+            let Edge { byte, to, next } = self.edges[edge as usize];
+            self.set(clone, byte, to);
+            edge = next;
+        }
+        clone
+    }
+
+    /// The standard online construction, appending a single byte.
+    fn push(&mut self, byte: u8) {
+        let cur = self.nodes.len() as u32;
+        self.nodes.push(Node {
+            len: self.nodes[self.last as usize].len + 1,
+            link: 0,
+            first: NONE,
+        });
+
+        let mut p = self.last;
+        while p != NONE && self.get(p, byte).is_none() {
+            self.set(p, byte, cur);
+            p = self.nodes[p as usize].link;
+        }
+
+        if p != NONE {
+            // the loop above only stops early at a state that has the transition.
+            let q = self.get(p, byte).unwrap();
+            if self.nodes[p as usize].len + 1 == self.nodes[q as usize].len {
+                self.nodes[cur as usize].link = q;
+            } else {
+                let clone = self.clone_node(q, self.nodes[p as usize].len + 1);
+                while p != NONE && self.get(p, byte) == Some(q) {
+                    self.set(p, byte, clone);
+                    p = self.nodes[p as usize].link;
+                }
+                self.nodes[q as usize].link = clone;
+                self.nodes[cur as usize].link = clone;
+            }
+        }
+
+        self.last = cur;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::rngs::StdRng;
+    use rand::{Rng, SeedableRng};
+
+    /// Checks every substring of `text` and random strings made of its words
+    /// against `str::contains`, which the overlap check used before.
+    fn assert_matches_contains(automaton: &SuffixAutomaton, text: &str) {
+        for start in 0..=text.len() {
+            for end in start..=text.len() {
+                if let Some(needle) = text.get(start..end) {
+                    assert!(automaton.contains(needle), "{needle:?} is in the text");
+                }
+            }
+        }
+        let words: Vec<&str> = text.split_whitespace().collect();
+        let mut rng = StdRng::seed_from_u64(0);
+        for _ in 0..2000 {
+            let len = rng.random_range(1..=4);
+            let needle = (0..len)
+                .map(|_| words[rng.random_range(0..words.len())])
+                .collect::<Vec<&str>>()
+                .join(" ");
+            assert_eq!(
+                automaton.contains(&needle),
+                text.contains(&needle),
+                "{needle:?}"
+            );
+        }
+    }
+
+    #[test]
+    fn contains_matches_str_contains() {
+        let text =
+            "the cat sat on the mat\nthe dog sat on the rug\na cat ran on a mat\ncafé au lait";
+        assert_matches_contains(&SuffixAutomaton::new(text), text);
+    }
+
+    #[test]
+    fn extending_matches_building_at_once() {
+        let sentences = [
+            "the cat sat on the mat",
+            "the dog sat on the rug",
+            "a cat ran",
+        ];
+        let mut automaton = SuffixAutomaton::new(sentences[0]);
+        let mut text = sentences[0].to_string();
+        for sentence in &sentences[1..] {
+            automaton.extend("\n");
+            automaton.extend(sentence);
+            text.push('\n');
+            text.push_str(sentence);
+        }
+        assert_matches_contains(&automaton, &text);
+    }
+}
@@ -1,15 +1,34 @@
-use crate::chain::Chain;
-use crate::chain::State;
+use std::borrow::Cow;
+use std::collections::{HashMap, HashSet};
+use std::sync::OnceLock;
+use std::time::{Duration, Instant};
+
+use crate::builder::TextBuilder;
+use crate::chain::{Chain, ChainOptions, SamplingStrategy, Smoothing, State};
+use crate::error::{GenerateError, MarukovError};
+use crate::splitter::SentenceSplitter;
+use crate::suffix::SuffixAutomaton;
+use crate::tokenizer::Tokenizer;
 use crate::vocab::Vocab;
 
-use rand::seq::SliceRandom;
+use rand::rngs::StdRng;
+use rand::seq::{IndexedRandom, SliceRandom};
+use rand::{Rng, SeedableRng};
 use regex::Regex;
 
 const MOR: f32 = 0.7; // max overlap ratio
 const MOT: usize = 15; // max overlap total
 
-const BEGIN: &str = "___BEGIN__";
-const END: &str = "___END__";
+const RELAX_STAGES: usize = 3; // stages of `TextOptions::relax_on_failure`
+
+const PUNCTUATION: &[char] = &['.', ',', '!', '?', ';', ':'];
+
+/// Separates sentences in `Text::rejoined_text`. Grams are joined with spaces and words never
+/// contain a line break, so the overlap check can't match a gram across two sentences.
+const SENTENCE_BREAK: &str = "\n";
+
+pub(crate) const BEGIN: &str = "___BEGIN__";
+pub(crate) const END: &str = "___END__";
 
 /// Options for generating text.
 #[derive(Debug, Clone)]
@@ -18,6 +37,52 @@ pub struct TextOptions {
     pub min_words: i32,
     pub max_words: i32,
     pub init_state: Option<State<u32>>,
+    /// Whether to reject outputs that overlap too much with the training data.
+    /// Turning this off returns the first candidate within the length bounds,
+    /// which may reproduce training sentences verbatim.
+    pub verify: bool,
+    /// Maximum share of the output's words that may be copied in a row from the training data.
+    pub max_overlap_ratio: f32,
+    /// Maximum number of words that may be copied in a row from the training data.
+    pub max_overlap_total: usize,
+    /// If the begin state has fewer than this many distinct continuations,
+    /// generation starts from a random frequent state instead, which adds variety to thin corpora.
+    /// `0` disables this.
+    pub cold_start_threshold: usize,
+    /// Which successors are considered at each step, see `SamplingStrategy`.
+    pub strategy: SamplingStrategy,
+    /// Controls how random the output is, see `ChainOptions::temperature`.
+    pub temperature: f32,
+    /// The string placed between words in the output, e.g. `""` for text that isn't space-separated.
+    /// The overlap check always compares against the training data joined with spaces,
+    /// so it is unaffected by this.
+    pub separator: String,
+    /// Words that must never appear in the output, candidates containing any of them are retried.
+    pub forbidden: Vec<String>,
+    /// If no candidate passes within `tries`, retry up to three more rounds of `tries` with
+    /// `max_overlap_ratio` raised towards 1.0, the last round without the overlap check at all,
+    /// instead of failing. Only applies to `Text::generate` and its seeded variants.
+    pub relax_on_failure: bool,
+    /// Minimum number of characters of the joined output, `0` disables this.
+    pub min_chars: usize,
+    /// Maximum number of characters of the joined output, `usize::MAX` disables this.
+    pub max_chars: usize,
+    /// A soft target for the number of words, sentences tend to end close to it
+    /// instead of being cut off, see `ChainOptions::target_tokens`.
+    /// `min_words` and `max_words` still apply on top of it.
+    pub target_words: Option<usize>,
+    /// Whether sentences are kept from ending before `min_words`, instead of only rejecting
+    /// the ones that did. Helps corpora with many short lines, where most candidates end early.
+    pub suppress_early_end: bool,
+    /// Stops trying once this much time has passed, whichever of it and `tries` runs out first.
+    /// Generation then fails with `GenerateError::Timeout`, or returns what it has so far
+    /// where it collects several candidates. `None` disables this.
+    pub time_budget: Option<Duration>,
+    /// Whether generation continues from a shorter context instead of stopping
+    /// at a state with no successors, see `ChainOptions::back_off`.
+    pub back_off: bool,
+    /// Whether a word is kept from following itself, see `ChainOptions::no_repeat`.
+    pub no_repeat: bool,
 }
 
 impl Default for TextOptions {
@@ -27,30 +92,122 @@ impl Default for TextOptions {
             min_words: 0,
             max_words: 100,
             init_state: None,
+            verify: true,
+            max_overlap_ratio: MOR,
+            max_overlap_total: MOT,
+            cold_start_threshold: 0,
+            strategy: SamplingStrategy::Full,
+            temperature: 1.0,
+            separator: String::from(" "),
+            forbidden: Vec::new(),
+            relax_on_failure: false,
+            min_chars: 0,
+            max_chars: usize::MAX,
+            target_words: None,
+            suppress_early_end: false,
+            time_budget: None,
+            back_off: false,
+            no_repeat: false,
         }
     }
 }
 
+impl TextOptions {
+    /// Returns the options for the chain, stopping after `max_tokens`.
+    fn chain_options(&self, max_tokens: usize) -> ChainOptions {
+        ChainOptions {
+            max_tokens: Some(max_tokens),
+            strategy: self.strategy,
+            temperature: self.temperature,
+            target_tokens: self.target_words,
+            min_tokens: if self.suppress_early_end {
+                self.min_words.max(0) as usize
+            } else {
+                0
+            },
+            back_off: self.back_off,
+            no_repeat: self.no_repeat,
+        }
+    }
+}
+
+/// Everything derived from `TextOptions` once per call, shared by all attempts.
+struct Prepared {
+    chain_options: ChainOptions,
+    forbidden: HashSet<u32>,
+    deadline: Option<Instant>,
+}
+
+impl Prepared {
+    /// Whether the time budget has run out.
+    fn expired(&self) -> bool {
+        self.deadline
+            .is_some_and(|deadline| Instant::now() >= deadline)
+    }
+}
+
+/// Explains how much of a generated text was copied from the training data.
+#[derive(Debug, Clone, Default)]
+pub struct Explanation {
+    /// The training sentence sharing the longest run of words with the generated text.
+    pub source: Option<String>,
+    /// The longest runs of words of the generated text that appear verbatim in `source`,
+    /// in order. Runs shorter than the chain's order plus one word are left out, as any
+    /// generated text is made of runs that short taken from the training data.
+    pub copied: Vec<String>,
+}
+
 /// Text is the main structure for generating text based on a Markov model.
-#[derive(Debug)]
-pub struct Text {
+/// Words are split and tokenized by `K`, see `Tokenizer`.
+#[derive(Debug, Clone)]
+pub struct Text<K = Vocab> {
     reject: Option<Regex>,
-    parsed_sentences: Vec<Vec<u32>>,
+    /// The trained sentences with their weights, 1 unless trained with weights.
+    /// Sentences with a weight of 0 or less are only in `rejoined_text`.
+    parsed_sentences: Vec<(Vec<u32>, i32)>,
+    /// The training sentences with their words joined by spaces, one sentence per line.
     rejoined_text: String,
     chain: Chain<u32>,
-    tokenizer: Vocab,
+    tokenizer: K,
+    splitter: SentenceSplitter,
+    split_punctuation: bool,
+    /// Whether input lines are transliterated with unidecode before matching `reject`.
+    unidecode_input: bool,
+    /// Whether sentences with fewer words than the chain's order are left out.
+    skip_short_sentences: bool,
+    /// Whether numeric words are left out, or replaced with `number_placeholder` if it is set.
+    strip_numbers: bool,
+    number_placeholder: Option<String>,
+    /// The model trained on reversed sentences, built on first use by `generate_ending_with`.
+    reversed: OnceLock<Chain<u32>>,
+    /// Index of `rejoined_text` for the overlap check, built on first use by `verify`.
+    corpus: OnceLock<SuffixAutomaton>,
 }
 
-impl Text {
-    /// Creates a default Text instance.
-    /// Do **NOT** use this, use `Text::new` instead.
-    fn default() -> Self {
+impl<K: Tokenizer> Text<K> {
+    /// Creates an untrained Text instance with the builder's settings.
+    /// Do **NOT** use this, use `TextBuilder::build` instead.
+    pub(crate) fn from_builder(builder: &TextBuilder, mut tokenizer: K) -> Self {
+        // reserved before any real word, so a corpus containing the sentinel strings
+        // gets ordinary tokens for them instead of colliding with the sentinels.
+        let begin = tokenizer.add_special(&builder.sentinels.0);
+        let end = tokenizer.add_special(&builder.sentinels.1);
         Self {
-            reject: None,
+            reject: builder.reject.clone(),
             parsed_sentences: Vec::with_capacity(0),
             rejoined_text: String::with_capacity(0),
-            chain: Chain::default(0, 0),
-            tokenizer: Vocab::new(),
+            chain: Chain::default(begin, end, builder.order)
+                .with_min_begin_weight(builder.min_begin_weight)
+                .with_smoothing(builder.smoothing),
+            tokenizer,
+            splitter: builder.splitter,
+            split_punctuation: builder.split_punctuation,
+            unidecode_input: builder.unidecode_input,
+            skip_short_sentences: builder.skip_short_sentences,
+            strip_numbers: builder.strip_numbers,
+            number_placeholder: builder.number_placeholder.clone(),
+            reversed: OnceLock::new(),
+            corpus: OnceLock::new(),
         }
     }
 
@@ -59,7 +216,11 @@ impl Text {
         if s.trim().is_empty() {
             return false;
         }
-        let decoded = unidecode::unidecode(s);
+        let decoded = if self.unidecode_input {
+            Cow::Owned(unidecode::unidecode(s))
+        } else {
+            Cow::Borrowed(s)
+        };
         if let Some(re) = &self.reject
             && re.is_match(&decoded)
         {
@@ -68,18 +229,62 @@ impl Text {
         true
     }
 
-    /// Verifies that the generated words do not overlap significantly with the original text.
-    fn verify(&self, words: &[String], mor: f32, mot: usize) -> bool {
-        let overlap_ratio = (mor * words.len() as f32).round() as usize;
+    /// Checks whether a word is a number, made only of digits and `,` or `.` separators.
+    fn is_number(word: &str) -> bool {
+        word.chars().any(|c| c.is_numeric())
+            && word.chars().all(|c| c.is_numeric() || c == ',' || c == '.')
+    }
+
+    /// Drops the numbers from a sentence's words, or replaces them with `placeholder`.
+    /// Punctuation after a number still ends the clause or sentence, so it is kept
+    /// on the placeholder, or moved to the word before the number.
+    fn without_numbers<'a>(words: Vec<&'a str>, placeholder: Option<&'a str>) -> Vec<Cow<'a, str>> {
+        let mut kept: Vec<Cow<str>> = Vec::with_capacity(words.len());
+        for word in words {
+            let stem = word.trim_end_matches(PUNCTUATION);
+            if !Self::is_number(stem) {
+                kept.push(Cow::Borrowed(word));
+                continue;
+            }
+            let punctuation = &word[stem.len()..];
+            match placeholder {
+                Some(placeholder) if punctuation.is_empty() => {
+                    kept.push(Cow::Borrowed(placeholder))
+                }
+                Some(placeholder) => kept.push(Cow::Owned(format!("{placeholder}{punctuation}"))),
+                None => {
+                    if let Some(previous) = kept.last_mut()
+                        && !punctuation.is_empty()
+                    {
+                        previous.to_mut().push_str(punctuation);
+                    }
+                }
+            }
+        }
+        kept
+    }
+
+    /// Returns the gram size and the number of grams the overlap check looks at.
+    fn overlap_window(len: usize, mor: f32, mot: usize) -> (usize, usize) {
+        let overlap_ratio = (mor * len as f32).round() as usize;
         let overlap_max = mot.min(overlap_ratio);
         let overlap_over = overlap_max + 1;
-        let gram_count = (words.len().saturating_sub(overlap_max)).max(1);
+        let gram_count = (len.saturating_sub(overlap_max)).max(1);
+        (overlap_over, gram_count)
+    }
+
+    /// Verifies that the generated words do not overlap significantly with the original text.
+    fn verify(&self, words: &[String], mor: f32, mot: usize) -> bool {
+        let (overlap_over, gram_count) = Self::overlap_window(words.len(), mor, mot);
+        let corpus = self
+            .corpus
+            .get_or_init(|| SuffixAutomaton::new(&self.rejoined_text));
 
         for i in 0..gram_count {
             let end = (i + overlap_over).min(words.len());
             let gram = &words[i..end];
-            let gram_joined = gram.join(" ");
-            if self.rejoined_text.contains(&gram_joined) {
+            let gram_joined = self.join_words(gram.iter().map(String::as_str), " ");
+            if corpus.contains(&gram_joined) {
                 return false;
             }
         }
@@ -87,26 +292,109 @@ impl Text {
         true
     }
 
-    /// Parses the input data into sentences.
-    fn parse(&mut self, data: String) -> (Vec<Vec<u32>>, String) {
-        let sentences: Vec<&str> = data
-            .split("\n")
-            .filter(|s| self.sentence_input(s))
-            .collect();
+    /// Checks words produced elsewhere against the training data, the same way `generate` does.
+    /// # Arguments
+    /// * `words` - The candidate's words, in the form they would be joined with spaces.
+    /// * `max_ratio` - Maximum share of the words that may be copied in a row, see `TextOptions`.
+    /// * `max_total` - Maximum number of words that may be copied in a row, see `TextOptions`.
+    /// # Returns
+    /// true if the candidate is novel enough, false if it copies too much of the training data.
+    pub fn check_overlap(&self, words: &[String], max_ratio: f32, max_total: usize) -> bool {
+        self.verify(words, max_ratio, max_total)
+    }
 
-        let rejoined = sentences.to_vec().join(" ");
+    /// Length of the longest run of tokens shared by both sequences.
+    fn longest_common_run(a: &[Option<u32>], b: &[u32]) -> usize {
+        let mut best = 0;
+        let mut prev = vec![0; b.len() + 1];
+        for x in a {
+            let mut cur = vec![0; b.len() + 1];
+            for (j, y) in b.iter().enumerate() {
+                if *x == Some(*y) {
+                    cur[j + 1] = prev[j] + 1;
+                    best = best.max(cur[j + 1]);
+                }
+            }
+            prev = cur;
+        }
+        best
+    }
 
-        (
-            sentences
-                .into_iter()
-                .map(|s| {
-                    s.split_whitespace()
-                        .map(|w| self.tokenizer.to_token(w))
-                        .collect()
-                })
-                .collect(),
-            rejoined,
-        )
+    /// Applies `strip_numbers` to words from user input, so they match the training data.
+    fn stripped<'a>(&'a self, words: Vec<&'a str>) -> Vec<Cow<'a, str>> {
+        if self.strip_numbers {
+            Self::without_numbers(words, self.number_placeholder.as_deref())
+        } else {
+            words.into_iter().map(Cow::Borrowed).collect()
+        }
+    }
+
+    /// Splits a sentence into words, separating trailing punctuation if enabled.
+    fn words<'a>(&self, s: &'a str) -> Vec<&'a str> {
+        if !self.split_punctuation {
+            return self.tokenizer.split(s);
+        }
+
+        let mut words = Vec::new();
+        for word in self.tokenizer.split(s) {
+            let stem = word.trim_end_matches(PUNCTUATION);
+            if stem.is_empty() {
+                words.push(word);
+                continue;
+            }
+            words.push(stem);
+            if stem.len() < word.len() {
+                words.push(&word[stem.len()..]);
+            }
+        }
+        words
+    }
+
+    /// Whether a word is a punctuation token that attaches to the word before it.
+    fn is_punctuation(&self, word: &str) -> bool {
+        self.split_punctuation && !word.is_empty() && word.chars().all(|c| PUNCTUATION.contains(&c))
+    }
+
+    /// Joins words back into text, attaching punctuation tokens to the word before them.
+    fn join_words<'a>(&self, words: impl Iterator<Item = &'a str>, separator: &str) -> String {
+        let mut joined = String::new();
+        for (i, word) in words.enumerate() {
+            if i > 0 && !self.is_punctuation(word) {
+                joined.push_str(separator);
+            }
+            joined.push_str(word);
+        }
+        joined
+    }
+
+    /// Parses the input lines into sentences, one line at a time.
+    fn parse<S: AsRef<str>>(&mut self, lines: impl Iterator<Item = S>) -> (Vec<Vec<u32>>, String) {
+        let mut sentences: Vec<Vec<u32>> = Vec::new();
+        let mut rejoined = String::new();
+
+        for line in lines {
+            // trims stray carriage returns along with the rest of the surrounding whitespace.
+            let line = line.as_ref().trim();
+            if !self.sentence_input(line) {
+                continue;
+            }
+            // the overlap check joins words with single spaces, so the text it searches must too.
+            let words: Vec<Cow<str>> = if self.strip_numbers {
+                Self::without_numbers(self.words(line), self.number_placeholder.as_deref())
+            } else {
+                self.words(line).into_iter().map(Cow::Borrowed).collect()
+            };
+            if self.skip_short_sentences && words.len() < self.chain.order() {
+                continue;
+            }
+            if !sentences.is_empty() {
+                rejoined.push_str(SENTENCE_BREAK);
+            }
+            rejoined.push_str(&self.join_words(words.iter().map(|w| w.as_ref()), " "));
+            sentences.push(words.iter().map(|w| self.tokenizer.to_token(w)).collect());
+        }
+
+        (sentences, rejoined)
     }
 }
 
@@ -117,69 +405,1271 @@ impl Text {
     /// # Returns
     /// A new instance of `Text`.
     pub fn new(data: String) -> Self {
-        let mut text = Text::default();
-        text.reject = Regex::new(&format!(r"(^')|('$)|\s'|'\s|[\{}(\(\)\[\])]", '"')).ok();
-        (text.parsed_sentences, text.rejoined_text) = text.parse(data);
-        text.chain = Chain::new(
-            &text.parsed_sentences,
-            text.tokenizer.to_token(BEGIN),
-            text.tokenizer.to_token(END),
-        );
-        text
+        TextBuilder::new().build(data)
+    }
+
+    /// Creates a new Text instance whose chain uses states of `order` words.
+    /// # Arguments
+    /// * `data` - A string containing the text data to be processed.
+    /// * `order` - The number of words in a state. Lower orders give more random output,
+    ///   higher orders stay closer to the source.
+    /// # Returns
+    /// A new instance of `Text`.
+    pub fn with_order(data: String, order: usize) -> Self {
+        TextBuilder::new().order(order).build(data)
+    }
+
+    /// Creates a new Text instance, splitting the data into sentences with the given splitter.
+    /// # Arguments
+    /// * `data` - A string containing the text data to be processed.
+    /// * `splitter` - How to split the data into sentences, see `SentenceSplitter`.
+    /// # Returns
+    /// A new instance of `Text`.
+    pub fn with_splitter(data: String, splitter: SentenceSplitter) -> Self {
+        TextBuilder::new().splitter(splitter).build(data)
+    }
+
+    /// Creates a new Text instance that keeps trailing punctuation (`.`, `,`, `!`, `?`, `;`, `:`)
+    /// as separate tokens, so the model learns where sentences end and the output
+    /// reads as punctuated sentences, e.g. `"Hello world."` becomes `["Hello", "world", "."]`.
+    /// # Arguments
+    /// * `data` - A string containing the text data to be processed.
+    /// # Returns
+    /// A new instance of `Text`.
+    pub fn with_punctuation(data: String) -> Self {
+        TextBuilder::new().split_punctuation(true).build(data)
+    }
+
+    /// Creates a new Text instance with a custom pattern for rejecting input lines.
+    /// # Arguments
+    /// * `data` - A string containing the text data to be processed.
+    /// * `reject` - Lines matching this pattern are left out of the model.
+    ///   `None` accepts every non-empty line, see `default_reject` for the default pattern.
+    /// # Returns
+    /// A new instance of `Text`.
+    pub fn with_reject(data: String, reject: Option<Regex>) -> Self {
+        TextBuilder::new().reject(reject).build(data)
+    }
+
+    /// Same as `with_reject`, but compiles the pattern from a string.
+    /// # Arguments
+    /// * `data` - A string containing the text data to be processed.
+    /// * `pattern` - Lines matching this regular expression are left out of the model.
+    /// # Returns
+    /// A new instance of `Text`, or `MarukovError::InvalidPattern` if the pattern doesn't compile.
+    pub fn with_reject_pattern(data: String, pattern: &str) -> Result<Self, MarukovError> {
+        Ok(TextBuilder::new().reject_pattern(pattern)?.build(data))
+    }
+
+    /// Creates a new Text instance whose begin and end tokens show as the given strings,
+    /// e.g. in exported models. They are reserved tokens, so they never match a word of the
+    /// data and never appear in the output, whatever they are.
+    /// # Arguments
+    /// * `data` - A string containing the text data to be processed.
+    /// * `begin` - The string shown for the token padding the start of each sentence.
+    /// * `end` - The string shown for the token marking the end of each sentence.
+    /// # Returns
+    /// A new instance of `Text`.
+    pub fn with_sentinels(data: String, begin: &str, end: &str) -> Self {
+        TextBuilder::new().sentinels(begin, end).build(data)
+    }
+
+    /// Returns the default pattern for rejecting input lines,
+    /// which drops lines with quotes, parentheses or brackets.
+    pub fn default_reject() -> Regex {
+        // the pattern is fixed and known to compile.
+        Regex::new(&format!(r"(^')|('$)|\s'|'\s|[\{}(\(\)\[\])]", '"')).unwrap()
+    }
+
+    /// Creates a new Text instance from a JSON array of sentences.
+    /// Each element is treated as one sentence, so sentences may contain newlines.
+    /// # Arguments
+    /// * `json` - A JSON array of strings, e.g. `["first sentence", "second sentence"]`.
+    /// # Returns
+    /// A new instance of `Text`, or the error if the input is not a JSON array of strings.
+    #[cfg(feature = "serde")]
+    pub fn from_json(json: &str) -> Result<Self, serde_json::Error> {
+        let sentences: Vec<String> = serde_json::from_str(json)?;
+        Ok(TextBuilder::new().build_from_sentences(sentences))
+    }
+
+    /// Creates a new Text instance from already separated sentences.
+    /// The sentences are consumed one at a time, so the corpus never has to be
+    /// concatenated into a single string first.
+    /// # Arguments
+    /// * `sentences` - The sentences to train on, e.g. lines read lazily from a file.
+    /// # Returns
+    /// A new instance of `Text`.
+    pub fn from_sentences(sentences: impl IntoIterator<Item = String>) -> Self {
+        TextBuilder::new().build_from_sentences(sentences)
+    }
+
+    /// Creates a new Text instance from sentences paired with weights,
+    /// where a sentence with weight `n` counts as if it appeared `n` times.
+    /// # Arguments
+    /// * `sentences` - The sentences to train on with their weights,
+    ///   sentences with a weight of 0 or less are only used for the overlap check.
+    /// # Returns
+    /// A new instance of `Text`.
+    pub fn from_weighted_sentences(sentences: impl IntoIterator<Item = (String, i32)>) -> Self {
+        TextBuilder::new().build_weighted(sentences)
+    }
+}
+
+impl<K: Tokenizer> Text<K> {
+    /// Creates a new Text instance that splits and tokenizes words with a custom tokenizer.
+    /// # Arguments
+    /// * `data` - A string containing the text data to be processed.
+    /// * `tokenizer` - An empty tokenizer, it is filled with the words of the data.
+    /// # Returns
+    /// A new instance of `Text`.
+    pub fn new_with_tokenizer(data: String, tokenizer: K) -> Self {
+        TextBuilder::new().build_with_tokenizer(data, tokenizer)
+    }
+
+    /// Builds the model from already separated sentences, using the settings of `self`.
+    pub(crate) fn train<S: AsRef<str>>(mut self, lines: impl Iterator<Item = S>) -> Self {
+        let (sentences, rejoined) = self.parse(lines);
+        self.chain = Chain::new(
+            &sentences,
+            *self.chain.begin_token(),
+            *self.chain.end_token(),
+            self.chain.order(),
+        )
+        .with_settings_of(&self.chain);
+        self.parsed_sentences = sentences.into_iter().map(|run| (run, 1)).collect();
+        self.rejoined_text = rejoined;
+        self
+    }
+
+    /// Same as `train`, but each sentence counts as many times as its weight.
+    pub(crate) fn train_weighted(mut self, sentences: impl Iterator<Item = (String, i32)>) -> Self {
+        let mut weighted: Vec<(Vec<u32>, i32)> = Vec::new();
+        for (sentence, weight) in sentences {
+            let (runs, rejoined) = self.parse(std::iter::once(sentence));
+            if !rejoined.is_empty() {
+                if !self.rejoined_text.is_empty() {
+                    self.rejoined_text.push_str(SENTENCE_BREAK);
+                }
+                self.rejoined_text.push_str(&rejoined);
+            }
+            weighted.extend(runs.into_iter().map(|run| (run, weight)));
+        }
+
+        self.chain = Chain::new_weighted(
+            &weighted,
+            *self.chain.begin_token(),
+            *self.chain.end_token(),
+            self.chain.order(),
+        )
+        .with_settings_of(&self.chain);
+        weighted.retain(|&(_, weight)| weight > 0);
+        self.parsed_sentences = weighted;
+        self
+    }
+
+    /// Forgets all training data while keeping the settings and the vocabulary,
+    /// so the model can be retrained with `add_text`.
+    pub fn clear(&mut self) {
+        self.chain.clear();
+        self.parsed_sentences.clear();
+        self.rejoined_text.clear();
+        self.reversed = OnceLock::new();
+        self.corpus = OnceLock::new();
+    }
+
+    /// Trains the model on more data, for updating it incrementally as new text arrives.
+    /// The data is split and filtered the same way as in the constructor.
+    /// # Arguments
+    /// * `data` - A string containing the text data to be added.
+    pub fn add_text(&mut self, data: String) {
+        let lines = self.splitter.split(&data);
+        let (sentences, rejoined) = self.parse(lines.into_iter());
+        self.chain.add_runs(&sentences);
+        self.parsed_sentences
+            .extend(sentences.into_iter().map(|run| (run, 1)));
+        self.reversed = OnceLock::new();
+        if !rejoined.is_empty() {
+            if !self.rejoined_text.is_empty() {
+                self.rejoined_text.push_str(SENTENCE_BREAK);
+                if let Some(corpus) = self.corpus.get_mut() {
+                    corpus.extend(SENTENCE_BREAK);
+                }
+            }
+            self.rejoined_text.push_str(&rejoined);
+            if let Some(corpus) = self.corpus.get_mut() {
+                corpus.extend(&rejoined);
+            }
+        }
     }
 
     /// Generates text based on the Markov model and the provided options.
     /// # Arguments
     /// * `options` - A `TextOptions` struct containing parameters for text generation.
     /// # Returns
-    /// An optional string containing the generated text.
-    pub fn generate(&self, options: TextOptions) -> Option<String> {
+    /// The generated text, or a `GenerateError` describing why nothing could be generated.
+    pub fn generate(&self, options: TextOptions) -> Result<String, GenerateError> {
+        self.generate_with_rng(options, &mut rand::rng())
+    }
+
+    /// Same as `generate`, but seeds the random number generator so the output is reproducible.
+    /// # Arguments
+    /// * `options` - A `TextOptions` struct containing parameters for text generation.
+    /// * `seed` - The seed for the random number generator.
+    /// # Returns
+    /// The generated text, or a `GenerateError` describing why nothing could be generated.
+    pub fn generate_seeded(
+        &self,
+        options: TextOptions,
+        seed: u64,
+    ) -> Result<String, GenerateError> {
+        self.generate_with_rng(options, &mut StdRng::seed_from_u64(seed))
+    }
+
+    /// Same as `generate`, but draws from the given random number generator.
+    pub fn generate_with_rng<R: Rng + ?Sized>(
+        &self,
+        options: TextOptions,
+        rng: &mut R,
+    ) -> Result<String, GenerateError> {
+        self.generate_tokens_with_rng(options, rng)
+            .map(|(text, _)| text)
+    }
+
+    /// Same as `generate`, but also returns the token IDs the text was joined from,
+    /// e.g. for scoring it again without splitting and tokenizing the text anew.
+    /// # Arguments
+    /// * `options` - A `TextOptions` struct containing parameters for text generation.
+    /// # Returns
+    /// The generated text and its tokens, which `tokenizer().to_word` maps back to the words,
+    /// or a `GenerateError` describing why nothing could be generated.
+    pub fn generate_tokens(
+        &self,
+        options: TextOptions,
+    ) -> Result<(String, Vec<u32>), GenerateError> {
+        self.generate_tokens_with_rng(options, &mut rand::rng())
+    }
+
+    /// Same as `generate_tokens`, but draws from the given random number generator.
+    pub fn generate_tokens_with_rng<R: Rng + ?Sized>(
+        &self,
+        options: TextOptions,
+        rng: &mut R,
+    ) -> Result<(String, Vec<u32>), GenerateError> {
+        let prepared = self.prepare(&options)?;
+        for _ in 0..options.tries {
+            if prepared.expired() {
+                return Err(GenerateError::Timeout);
+            }
+            if let Some(tokens) = self.attempt(&options, &prepared, rng) {
+                return Ok((self.join(&tokens, &options.separator), tokens));
+            }
+        }
+        if !options.relax_on_failure || !options.verify {
+            return Err(GenerateError::ExhaustedTries);
+        }
+
+        // loosen the overlap check step by step, and finally drop it.
+        let mut relaxed = options.clone();
+        for stage in 1..=RELAX_STAGES {
+            let progress = stage as f32 / RELAX_STAGES as f32;
+            relaxed.max_overlap_ratio =
+                options.max_overlap_ratio + (1.0 - options.max_overlap_ratio).max(0.0) * progress;
+            relaxed.verify = stage < RELAX_STAGES;
+            for _ in 0..options.tries {
+                if prepared.expired() {
+                    return Err(GenerateError::Timeout);
+                }
+                if let Some(tokens) = self.attempt(&relaxed, &prepared, rng) {
+                    return Ok((self.join(&tokens, &options.separator), tokens));
+                }
+            }
+        }
+
+        Err(GenerateError::ExhaustedTries)
+    }
+
+    /// Generates text that isn't in `seen` yet and adds it there, so repeated calls
+    /// never return the same text twice.
+    /// # Arguments
+    /// * `seen` - The texts returned so far, e.g. by earlier calls.
+    /// * `options` - A `TextOptions` struct containing parameters for text generation.
+    /// # Returns
+    /// The generated text, or a `GenerateError` describing why nothing could be generated.
+    /// `GenerateError::ExhaustedTries` is returned once the model has nothing new left to offer.
+    pub fn generate_unique(
+        &self,
+        seen: &mut HashSet<String>,
+        options: TextOptions,
+    ) -> Result<String, GenerateError> {
+        let prepared = self.prepare(&options)?;
+        let mut rng = rand::rng();
         for _ in 0..options.tries {
-            let tokens: Vec<u32> = self.chain.generate(options.init_state.clone());
-            if tokens.len() > options.max_words as usize
-                || tokens.len() < options.min_words as usize
-            {
+            if prepared.expired() {
+                return Err(GenerateError::Timeout);
+            }
+            let Some(tokens) = self.attempt(&options, &prepared, &mut rng) else {
                 continue;
+            };
+            let text = self.join(&tokens, &options.separator);
+            if !seen.contains(&text) {
+                seen.insert(text.clone());
+                return Ok(text);
             }
+        }
 
-            let words: Vec<String> = tokens
-                .iter()
-                .map(|&token| self.tokenizer.to_word(token).to_string())
-                .collect();
+        Err(GenerateError::ExhaustedTries)
+    }
 
-            if self.verify(&words, MOR, MOT) {
-                return Some(words.join(" "));
+    /// Generates a batch of up to `n` distinct texts.
+    /// # Arguments
+    /// * `n` - The number of texts to generate.
+    /// * `options` - A `TextOptions` struct containing parameters for text generation.
+    ///   `tries` bounds the total number of attempts for the whole batch.
+    /// # Returns
+    /// The generated texts, fewer than `n` if the model couldn't produce enough distinct ones.
+    pub fn generate_n(&self, n: usize, options: TextOptions) -> Vec<String> {
+        let Ok(prepared) = self.prepare(&options) else {
+            return Vec::new();
+        };
+        let mut rng = rand::rng();
+        let mut seen = HashSet::new();
+        let mut generated = Vec::new();
+
+        for _ in 0..options.tries {
+            if generated.len() >= n || prepared.expired() {
+                break;
+            }
+            let Some(tokens) = self.attempt(&options, &prepared, &mut rng) else {
+                continue;
+            };
+            let text = self.join(&tokens, &options.separator);
+            if seen.insert(text.clone()) {
+                generated.push(text);
             }
         }
 
-        None
+        generated
     }
 
-    /// Generates text starting with a specific word.
+    /// Generates text that contains the given word or phrase somewhere.
     /// # Arguments
-    /// * `start` - The starting word for the generated text.
+    /// * `keyword` - The word(s) that have to appear in the output, in order.
     /// * `options` - A `TextOptions` struct containing parameters for text generation.
     /// # Returns
-    /// An optional string containing the generated text.
-    pub fn generate_with_start(&self, start: &str, options: TextOptions) -> Option<String> {
-        if let Some(token) = self.tokenizer.to_token_opt(start)
-            && let Some(mut init_states) = self.chain.find_init_states(token)
+    /// The generated text, or a `GenerateError` describing why nothing could be generated.
+    /// `GenerateError::ConstraintsUnsatisfiable` is returned if the keyword is not in the model.
+    pub fn generate_containing(
+        &self,
+        keyword: &str,
+        options: TextOptions,
+    ) -> Result<String, GenerateError> {
+        let prepared = self.prepare(&options)?;
+        let keyword: Vec<u32> = self
+            .tokenize_context(keyword)
+            .filter(|tokens| !tokens.is_empty())
+            .ok_or(GenerateError::ConstraintsUnsatisfiable)?;
+
+        let mut rng = rand::rng();
+        for _ in 0..options.tries {
+            if prepared.expired() {
+                return Err(GenerateError::Timeout);
+            }
+            let Some(tokens) = self.attempt(&options, &prepared, &mut rng) else {
+                continue;
+            };
+            if tokens.windows(keyword.len()).any(|w| w == keyword) {
+                return Ok(self.join(&tokens, &options.separator));
+            }
+        }
+
+        Err(GenerateError::ExhaustedTries)
+    }
+
+    /// Lazily yields the words of a single generated sentence, e.g. for a typing effect.
+    /// Since the words are handed out as they are sampled, the sentence is not retried
+    /// or verified against the corpus; only `init_state`, `max_words`, `max_chars`, `separator`,
+    /// `strategy`, `temperature`, `target_words`, `suppress_early_end`, `back_off` and `no_repeat`
+    /// are taken from the options.
+    /// The stream ends before a word that would make the joined words longer than `max_chars`,
+    /// so it never overshoots the budget and only ever hands out whole words.
+    /// # Arguments
+    /// * `options` - A `TextOptions` struct containing parameters for text generation.
+    /// # Returns
+    /// An iterator over the generated words.
+    pub fn generate_iter(&self, options: &TextOptions) -> impl Iterator<Item = &str> + '_ {
+        self.generate_iter_with_rng(options, rand::rng())
+    }
+
+    /// Same as `generate_iter`, but draws from the given random number generator.
+    pub fn generate_iter_with_rng<'a, R: Rng + 'a>(
+        &'a self,
+        options: &TextOptions,
+        rng: R,
+    ) -> impl Iterator<Item = &'a str> + 'a {
+        let chain_options = options.chain_options(options.max_words.max(0) as usize);
+        let budget = options.max_chars;
+        let separator = options.separator.chars().count();
+        let mut used: Option<usize> = None;
+        self.chain
+            .iter_with_options(options.init_state.clone(), chain_options, rng)
+            .map(|token| self.tokenizer.to_word(token))
+            .map_while(move |word| {
+                // counted the same way `join` lays the words out.
+                let gap = match used {
+                    Some(_) if !self.is_punctuation(word) => separator,
+                    _ => 0,
+                };
+                let total = used.unwrap_or(0) + gap + word.chars().count();
+                if total > budget {
+                    return None;
+                }
+                used = Some(total);
+                Some(word)
+            })
+    }
+
+    /// Generates up to `n` valid candidates and returns the one the model finds most likely,
+    /// which tends to be more fluent than the first candidate that passes.
+    /// # Arguments
+    /// * `n` - The number of valid candidates to collect before picking one.
+    /// * `options` - A `TextOptions` struct containing parameters for text generation.
+    ///   `tries` bounds the total number of attempts.
+    /// # Returns
+    /// The highest-scoring candidate, or a `GenerateError` if none passed the checks.
+    pub fn generate_best_of(
+        &self,
+        n: usize,
+        options: TextOptions,
+    ) -> Result<String, GenerateError> {
+        let prepared = self.prepare(&options)?;
+        let mut rng = rand::rng();
+        let mut best: Option<(f64, Vec<u32>)> = None;
+        let mut found = 0;
+        let mut error = GenerateError::ExhaustedTries;
+
+        for _ in 0..options.tries {
+            if found >= n.max(1) {
+                break;
+            }
+            if prepared.expired() {
+                error = GenerateError::Timeout;
+                break;
+            }
+            let Some(tokens) = self.attempt(&options, &prepared, &mut rng) else {
+                continue;
+            };
+            found += 1;
+            let score = self
+                .chain
+                .sequence_log_prob(&tokens)
+                .unwrap_or(f64::NEG_INFINITY);
+            if best.as_ref().is_none_or(|(top, _)| score > *top) {
+                best = Some((score, tokens));
+            }
+        }
+
+        best.map(|(_, tokens)| self.join(&tokens, &options.separator))
+            .ok_or(error)
+    }
+
+    /// Checks the options against the model and derives what every attempt needs.
+    fn prepare(&self, options: &TextOptions) -> Result<Prepared, GenerateError> {
+        if self.chain.is_empty() {
+            return Err(GenerateError::EmptyModel);
+        }
+        if options.max_words <= 0
+            || options.min_words > options.max_words
+            || options.min_chars > options.max_chars
         {
-            let mut rng = rand::rng();
-            init_states.shuffle(&mut rng);
+            return Err(GenerateError::ConstraintsUnsatisfiable);
+        }
+
+        // anything longer than one past the limit is rejected anyway, no need to keep going.
+        let max_tokens = (options.max_words as usize).saturating_add(1);
+        let chain_options = options.chain_options(max_tokens);
+        // words that were never seen can't be generated, so they are simply left out.
+        let forbidden = options
+            .forbidden
+            .iter()
+            .filter_map(|w| self.tokenizer.to_token_opt(w))
+            .collect();
+        Ok(Prepared {
+            chain_options,
+            forbidden,
+            // a budget too large to represent as an instant is as good as none.
+            deadline: options
+                .time_budget
+                .and_then(|budget| Instant::now().checked_add(budget)),
+        })
+    }
+
+    /// Samples one candidate and checks it with `accept`.
+    /// # Returns
+    /// The candidate's tokens if it passed, None otherwise.
+    fn attempt<R: Rng + ?Sized>(
+        &self,
+        options: &TextOptions,
+        prepared: &Prepared,
+        rng: &mut R,
+    ) -> Option<Vec<u32>> {
+        let chain_options = &prepared.chain_options;
+        let cold_start = options.init_state.is_none()
+            && self.chain.begin_branching() < options.cold_start_threshold;
+        let cold_state = if cold_start {
+            self.chain.random_state(rng)
+        } else {
+            None
+        };
+
+        let tokens: Vec<u32> = match cold_state {
+            Some(state) => {
+                let begin = *self.chain.begin_token();
+                let mut tokens: Vec<u32> = state.iter().filter(|&&t| t != begin).cloned().collect();
+                let mut chain_options = chain_options.clone();
+                chain_options.max_tokens = chain_options
+                    .max_tokens
+                    .map(|max| max.saturating_sub(tokens.len()));
+                tokens.extend(
+                    self.chain
+                        .generate_with_options(Some(state), &chain_options, rng),
+                );
+                tokens
+            }
+            None => {
+                self.chain
+                    .generate_with_options(options.init_state.clone(), chain_options, rng)
+            }
+        };
+        self.accept(options, prepared, &tokens).then_some(tokens)
+    }
+
+    /// Checks a candidate against the length, blocklist and overlap constraints.
+    fn accept(&self, options: &TextOptions, prepared: &Prepared, tokens: &[u32]) -> bool {
+        if tokens.len() > options.max_words as usize || tokens.len() < options.min_words as usize {
+            return false;
+        }
+        if tokens.iter().any(|t| prepared.forbidden.contains(t)) {
+            return false;
+        }
+        if options.min_chars > 0 || options.max_chars < usize::MAX {
+            let chars = self.join(tokens, &options.separator).chars().count();
+            if chars < options.min_chars || chars > options.max_chars {
+                return false;
+            }
+        }
+
+        // a token without a word can only come from a corrupt model, never emit it as an empty word.
+        let Some(words) = tokens
+            .iter()
+            .map(|&token| self.tokenizer.to_word_opt(token).map(str::to_string))
+            .collect::<Option<Vec<String>>>()
+        else {
+            return false;
+        };
+
+        !options.verify || self.verify(&words, options.max_overlap_ratio, options.max_overlap_total)
+    }
+
+    /// Joins tokens back into text.
+    fn join(&self, tokens: &[u32], separator: &str) -> String {
+        self.join_words(
+            tokens.iter().map(|&token| self.tokenizer.to_word(token)),
+            separator,
+        )
+    }
 
-            for init_state in init_states {
-                let mut opts = options.clone();
-                opts.init_state = Some(init_state);
+    /// Finds the states generation can continue from after the given tokens.
+    /// If there are fewer tokens than the chain's order, the tokens are first tried as
+    /// the start of a sentence, then as the tail of any state in the model.
+    fn start_states_for(&self, chain: &Chain<u32>, tokens: &[u32]) -> Vec<State<u32>> {
+        let order = chain.order();
+        if tokens.len() >= order {
+            let state = tokens[tokens.len() - order..].to_vec();
+            if !chain.contains_state(&state) {
+                return Vec::new();
+            }
+            return vec![state];
+        }
 
-                if let Some(generated) = self.generate(opts)
-                    && !generated.is_empty()
-                {
-                    return Some(format!("{} {}", start, generated));
+        let mut padded = vec![*chain.begin_token(); order - tokens.len()];
+        padded.extend(tokens);
+        if chain.contains_state(&padded) {
+            return vec![padded];
+        }
+
+        let mut states: Vec<State<u32>> = tokens
+            .last()
+            .and_then(|&token| chain.find_init_states(token))
+            .unwrap_or_default()
+            .into_iter()
+            .filter(|state| state.ends_with(tokens))
+            .collect();
+        states.shuffle(&mut rand::rng());
+        states
+    }
+
+    /// Converts user-supplied words to tokens without adding anything to the vocabulary,
+    /// splitting them the same way as the training data.
+    /// # Returns
+    /// The tokens, or None if any of the words is not in the vocabulary.
+    pub fn tokenize_context(&self, words: &str) -> Option<Vec<u32>> {
+        self.stripped(self.words(words))
+            .iter()
+            .map(|w| self.tokenizer.to_token_opt(w))
+            .collect()
+    }
+
+    /// Returns the state formed by the last words of the context,
+    /// padded with the begin token if there are fewer words than the chain's order.
+    /// An empty context gives the begin state, where a new sentence starts.
+    /// # Returns
+    /// The state, or None if any of the words is not in the vocabulary.
+    pub fn context_state(&self, context: &str) -> Option<State<u32>> {
+        let tokens: Vec<u32> = self.tokenize_context(context)?;
+        let order = self.chain.order();
+        let mut state = vec![*self.chain.begin_token(); order.saturating_sub(tokens.len())];
+        state.extend(&tokens[tokens.len().saturating_sub(order)..]);
+        Some(state)
+    }
+
+    /// Converts a state back to the words it holds, the inverse of `context_state`.
+    /// The begin tokens padding the start of a sentence are left out.
+    pub fn state_words(&self, state: &State<u32>) -> String {
+        let begin = *self.chain.begin_token();
+        let tokens: Vec<u32> = state.iter().filter(|&&t| t != begin).cloned().collect();
+        self.join(&tokens, " ")
+    }
+
+    /// Checks whether generation can continue from the given state.
+    pub fn contains_state(&self, state: &State<u32>) -> bool {
+        self.chain.contains_state(state)
+    }
+
+    /// Advances generation by a single word, for generating a sentence in increments.
+    /// Pass the state to each call in turn, it can be stored in between, e.g. as words
+    /// with `state_words` and turned back with `context_state`.
+    /// Unlike `generate`, the output is not checked against any `TextOptions`.
+    /// # Arguments
+    /// * `state` - The state to continue from, moved forward by the generated word.
+    /// # Returns
+    /// The next word, or None once the sentence has ended, in which case `state` is unchanged.
+    pub fn generate_step(&self, state: &mut State<u32>) -> Option<String> {
+        self.generate_step_with_rng(state, &mut rand::rng())
+    }
+
+    /// Same as `generate_step`, but draws from the given random number generator.
+    pub fn generate_step_with_rng<R: Rng + ?Sized>(
+        &self,
+        state: &mut State<u32>,
+        rng: &mut R,
+    ) -> Option<String> {
+        let token = self
+            .chain
+            .next_with_rng(state, rng)
+            .filter(|t| t != self.chain.end_token())?;
+        state.remove(0);
+        state.push(token);
+        Some(self.tokenizer.to_word(token).to_string())
+    }
+
+    /// Returns the most likely next words after the given context, most likely first.
+    /// # Arguments
+    /// * `context` - The words typed so far, see `suggest_next`.
+    /// * `n` - The maximum number of words to return.
+    /// # Returns
+    /// Up to `n` words with their probabilities. The end of the sentence is left out,
+    /// so the probabilities may add up to less than 1.
+    pub fn top_next(&self, context: &str, n: usize) -> Vec<(String, f64)> {
+        let Some(state) = self.context_state(context) else {
+            return Vec::new();
+        };
+        let end = *self.chain.end_token();
+        self.chain
+            .top_successors(&state, n.saturating_add(1))
+            .into_iter()
+            .filter(|&(token, _)| token != end)
+            .take(n)
+            .map(|(token, p)| (self.tokenizer.to_word(token).to_string(), p))
+            .collect()
+    }
+
+    /// Suggests a likely next word after the given context, e.g. for autocompletion.
+    /// # Arguments
+    /// * `context` - The words typed so far, only the last ones up to the chain's order are used.
+    ///   Contexts shorter than that are treated as the start of a sentence.
+    /// # Returns
+    /// A word sampled by how often it followed the context, or None if the context is unknown
+    /// or only ever ended a sentence.
+    pub fn suggest_next(&self, context: &str) -> Option<String> {
+        let state = self.context_state(context)?;
+        let end = *self.chain.end_token();
+        let successors: Vec<(u32, i32)> = self
+            .chain
+            .successors(&state)?
+            .iter()
+            .filter(|&(&token, _)| token != end)
+            .map(|(&token, &count)| (token, count))
+            .collect();
+        let (token, _) = successors
+            .choose_weighted(&mut rand::rng(), |&(_, count)| count)
+            .ok()?;
+        Some(self.tokenizer.to_word(*token).to_string())
+    }
+
+    /// Finds the states generation can continue from after the given word or phrase,
+    /// for use as `TextOptions::init_state`.
+    /// # Arguments
+    /// * `start` - The starting word(s).
+    /// # Returns
+    /// The candidate states in random order, or None if the model can't continue from `start`.
+    pub fn start_states(&self, start: &str) -> Option<Vec<State<u32>>> {
+        let tokens: Vec<u32> = self.tokenize_context(start)?;
+        if tokens.is_empty() {
+            return None;
+        }
+        let states = self.start_states_for(&self.chain, &tokens);
+        (!states.is_empty()).then_some(states)
+    }
+
+    /// Generates text starting with a specific word or phrase.
+    /// # Arguments
+    /// * `start` - The starting word(s) for the generated text.
+    /// * `options` - A `TextOptions` struct containing parameters for text generation.
+    /// # Returns
+    /// The starting words followed by the generated text, or a `GenerateError`.
+    /// `GenerateError::UnknownStart` is returned if the model has no state to continue from.
+    pub fn generate_with_start(
+        &self,
+        start: &str,
+        options: TextOptions,
+    ) -> Result<String, GenerateError> {
+        if self.chain.is_empty() {
+            return Err(GenerateError::EmptyModel);
+        }
+        let words = self.words(start);
+        let init_states = self
+            .start_states(start)
+            .ok_or(GenerateError::UnknownStart)?;
+
+        // the budget is shared by all the states, not restarted for each of them.
+        let started = Instant::now();
+        let mut error = GenerateError::ExhaustedTries;
+        for init_state in init_states {
+            let mut opts = options.clone();
+            opts.init_state = Some(init_state);
+            opts.time_budget = options
+                .time_budget
+                .map(|budget| budget.saturating_sub(started.elapsed()));
+
+            match self.generate(opts) {
+                Ok(generated) if !generated.is_empty() => {
+                    let mut joined = self.join_words(words.iter().cloned(), &options.separator);
+                    if !self
+                        .words(&generated)
+                        .first()
+                        .is_some_and(|w| self.is_punctuation(w))
+                    {
+                        joined.push_str(&options.separator);
+                    }
+                    joined.push_str(&generated);
+                    return Ok(joined);
                 }
+                Ok(_) => {}
+                Err(GenerateError::Timeout) => return Err(GenerateError::Timeout),
+                Err(e) => error = e,
+            }
+        }
+
+        Err(error)
+    }
+
+    /// Generates the next sentence of a passage, continuing from the last words of `previous`
+    /// as if the sentences ran on, so consecutive outputs flow into each other.
+    /// The new sentence is kept from ending right away, see `TextOptions::suppress_early_end`.
+    /// # Arguments
+    /// * `previous` - The text so far, usually the last generated sentence.
+    /// * `options` - A `TextOptions` struct containing parameters for text generation,
+    ///   its `init_state` is replaced.
+    /// # Returns
+    /// The generated text without `previous`, or a `GenerateError`.
+    /// If the model can't continue from the last words of `previous`, e.g. because they only
+    /// ever ended a sentence, generation starts from the begin state like `generate` instead.
+    pub fn generate_continuation(
+        &self,
+        previous: &str,
+        options: TextOptions,
+    ) -> Result<String, GenerateError> {
+        let words = self.stripped(self.words(previous));
+        let order = self.chain.order();
+        let end = *self.chain.end_token();
+        let tail: Option<Vec<u32>> = words[words.len().saturating_sub(order)..]
+            .iter()
+            .map(|w| self.tokenizer.to_token_opt(w))
+            .collect();
+        let state = tail.filter(|tail| !tail.is_empty()).map(|tail| {
+            let mut state = vec![*self.chain.begin_token(); order - tail.len()];
+            state.extend(tail);
+            state
+        });
+
+        let mut opts = options;
+        opts.init_state = None;
+        if let Some(state) = state.filter(|state| {
+            self.chain
+                .successors(state)
+                .is_some_and(|weights| weights.keys().any(|&t| t != end))
+        }) {
+            opts.init_state = Some(state);
+            opts.min_words = opts.min_words.max(1);
+            opts.suppress_early_end = true;
+        }
+        self.generate(opts)
+    }
+
+    /// Returns the model trained on reversed sentences, building it on first use.
+    fn reversed_chain(&self) -> &Chain<u32> {
+        self.reversed.get_or_init(|| {
+            let runs: Vec<(Vec<u32>, i32)> = self
+                .parsed_sentences
+                .iter()
+                .map(|(sentence, weight)| (sentence.iter().rev().cloned().collect(), *weight))
+                .collect();
+            Chain::new_weighted(
+                &runs,
+                *self.chain.begin_token(),
+                *self.chain.end_token(),
+                self.chain.order(),
+            )
+        })
+    }
+
+    /// Generates text ending with a specific word or phrase, by generating backwards from it.
+    /// The backward model is built on the first call and reused afterwards.
+    /// # Arguments
+    /// * `end` - The final word(s) of the generated text.
+    /// * `options` - A `TextOptions` struct containing parameters for text generation.
+    ///   Unlike `generate_with_start`, the word bounds include the final words.
+    /// # Returns
+    /// The generated text followed by the final words, or a `GenerateError`.
+    /// `GenerateError::UnknownStart` is returned if the model has no state to continue from.
+    pub fn generate_ending_with(
+        &self,
+        end: &str,
+        options: TextOptions,
+    ) -> Result<String, GenerateError> {
+        let prepared = self.prepare(&options)?;
+        let tokens: Vec<u32> = self
+            .tokenize_context(end)
+            .ok_or(GenerateError::UnknownStart)?;
+        let reversed: Vec<u32> = tokens.iter().rev().cloned().collect();
+
+        let chain = self.reversed_chain();
+        let init_states = self.start_states_for(chain, &reversed);
+        if tokens.is_empty() || init_states.is_empty() {
+            return Err(GenerateError::UnknownStart);
+        }
+
+        let mut rng = rand::rng();
+        for _ in 0..options.tries {
+            if prepared.expired() {
+                return Err(GenerateError::Timeout);
+            }
+            let Some(init_state) = init_states.choose(&mut rng) else {
+                break;
+            };
+            let mut candidate: Vec<u32> = chain
+                .generate_with_options(Some(init_state.clone()), &prepared.chain_options, &mut rng)
+                .into_iter()
+                .rev()
+                .collect();
+            candidate.extend(&tokens);
+            if self.accept(&options, &prepared, &candidate) {
+                return Ok(self.join(&candidate, &options.separator));
+            }
+        }
+
+        Err(GenerateError::ExhaustedTries)
+    }
+
+    /// Scores a text by how likely the model is to produce it, for ranking candidates.
+    /// # Arguments
+    /// * `text` - The text to score, split into words the same way as the training data.
+    /// # Returns
+    /// The log-probability of the text, or None if it contains unknown words or transitions
+    /// and there is no smoothing, see `TextBuilder::smoothing`.
+    pub fn score(&self, text: &str) -> Option<f64> {
+        let tokens: Vec<u32> = self.scored_tokens(text)?;
+        self.chain.sequence_log_prob(&tokens)
+    }
+
+    /// Tokenizes a text for scoring. With smoothing, unknown words get a token that no word
+    /// has, so they are scored as unseen transitions instead of failing.
+    fn scored_tokens(&self, text: &str) -> Option<Vec<u32>> {
+        if self.chain.smoothing() == Smoothing::None {
+            return self.tokenize_context(text);
+        }
+        let unseen = self.unseen_token();
+        Some(
+            self.stripped(self.words(text))
+                .iter()
+                .map(|w| self.tokenizer.to_token_opt(w).unwrap_or(unseen))
+                .collect(),
+        )
+    }
+
+    /// Returns a token that no word or marker maps to, for words scored as unseen.
+    /// A custom tokenizer may hand out any IDs, so this looks for a free one from the top.
+    fn unseen_token(&self) -> u32 {
+        let begin = *self.chain.begin_token();
+        let end = *self.chain.end_token();
+        (0..=u32::MAX)
+            .rev()
+            .find(|&t| t != begin && t != end && self.tokenizer.to_word_opt(t).is_none())
+            .unwrap_or(u32::MAX)
+    }
+
+    /// Measures how well the model predicts a held-out text, lower is better.
+    /// The text is split into sentences and filtered the same way as the training data,
+    /// every word and the end of each sentence counts as a predicted token.
+    /// # Arguments
+    /// * `held_out` - Text that was not trained on, e.g. a part of the corpus set aside.
+    /// # Returns
+    /// `exp(-log_prob / tokens)` over all sentences, or None if there are no sentences
+    /// or, without smoothing, any of them contains a word or transition the model has never seen.
+    pub fn perplexity(&self, held_out: &str) -> Option<f64> {
+        let mut log_prob = 0.0;
+        let mut tokens = 0;
+        for line in self.splitter.split(held_out) {
+            let line = line.trim();
+            if !self.sentence_input(line) {
+                continue;
             }
+            let sentence = self.scored_tokens(line)?;
+            log_prob += self.chain.sequence_log_prob(&sentence)?;
+            tokens += sentence.len() + 1;
         }
+        if tokens == 0 {
+            return None;
+        }
+        Some((-log_prob / tokens as f64).exp())
+    }
+
+    /// Returns the tokenizer mapping the words of the model to their token IDs.
+    pub fn tokenizer(&self) -> &K {
+        &self.tokenizer
+    }
+
+    /// Returns the number of distinct words learned from the training data.
+    pub fn vocab_size(&self) -> usize {
+        self.tokenizer.len() - self.tokenizer.special_count()
+    }
+
+    /// Returns the total number of words in the training data,
+    /// each sentence counting as many times as its weight.
+    pub fn token_count(&self) -> usize {
+        self.parsed_sentences
+            .iter()
+            .map(|(sentence, weight)| sentence.len() * *weight as usize)
+            .sum()
+    }
+
+    /// Counts how often each word appears in the training data.
+    /// # Returns
+    /// Every word with its count, most frequent first and alphabetically among equal counts.
+    /// Each sentence counts as many times as its weight.
+    pub fn word_frequencies(&self) -> Vec<(String, usize)> {
+        let mut counts: HashMap<u32, usize> = HashMap::new();
+        for (sentence, weight) in &self.parsed_sentences {
+            for &token in sentence {
+                *counts.entry(token).or_insert(0) += *weight as usize;
+            }
+        }
+
+        let mut frequencies: Vec<(String, usize)> = counts
+            .into_iter()
+            .map(|(token, count)| (self.tokenizer.to_word(token).to_string(), count))
+            .collect();
+        frequencies.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+        frequencies
+    }
 
-        None
+    /// Returns the state made of the given words, padded with the begin token like `context_state`.
+    /// None if a word is unknown or there are more words than the chain's order.
+    fn words_state(&self, words: &[&str]) -> Option<State<u32>> {
+        let words = self.stripped(words.to_vec());
+        let order = self.chain.order();
+        if words.len() > order {
+            return None;
+        }
+        let mut state = vec![*self.chain.begin_token(); order - words.len()];
+        for word in &words {
+            state.push(self.tokenizer.to_token_opt(word)?);
+        }
+        Some(state)
+    }
+
+    /// Counts how often a sequence of words was followed by anything in the training data,
+    /// including the end of a sentence.
+    /// # Arguments
+    /// * `words` - As many words as the chain's order, fewer are taken as the start of a sentence.
+    /// # Returns
+    /// The total count, 0 if the words never appeared in that order.
+    pub fn ngram_count(&self, words: &[&str]) -> i32 {
+        self.words_state(words)
+            .and_then(|state| self.chain.successors(&state))
+            .map_or(0, |weights| {
+                weights
+                    .values()
+                    .map(|&c| c as i64)
+                    .sum::<i64>()
+                    .min(i32::MAX as i64) as i32
+            })
+    }
+
+    /// Counts how often a sequence of words was followed by a specific word in the training data.
+    /// # Arguments
+    /// * `context` - The preceding words, see `ngram_count`.
+    /// * `next` - The following word.
+    /// # Returns
+    /// The count of the transition, 0 if it never happened.
+    pub fn transition_count(&self, context: &[&str], next: &str) -> i32 {
+        let Some(next) = self.tokenizer.to_token_opt(next) else {
+            return 0;
+        };
+        self.words_state(context)
+            .and_then(|state| self.chain.successors(&state)?.get(&next).copied())
+            .unwrap_or(0)
+    }
+
+    /// Checks whether a word is known to the model, i.e. it is in the vocabulary
+    /// and appears in at least one state of the chain.
+    /// Useful for warning users before calling `generate_with_start` with a word that can't continue.
+    pub fn knows_word(&self, word: &str) -> bool {
+        self.tokenizer
+            .to_token_opt(word)
+            .and_then(|token| self.chain.find_init_states(token))
+            .is_some()
+    }
+
+    /// Exports the underlying model as a flat edge list, with tokens decoded back into words.
+    /// # Returns
+    /// One `(state, next word, weight)` tuple per transition in the model.
+    pub fn to_edge_list(&self) -> Vec<(Vec<String>, String, i32)> {
+        self.chain
+            .to_edge_list()
+            .into_iter()
+            .map(|(state, next, weight)| {
+                (
+                    state
+                        .iter()
+                        .map(|&t| self.tokenizer.to_word(t).to_string())
+                        .collect(),
+                    self.tokenizer.to_word(next).to_string(),
+                    weight,
+                )
+            })
+            .collect()
+    }
+
+    /// Explains which training sentence a generated text was most likely copied from.
+    /// # Arguments
+    /// * `generated` - A text, usually one returned by `generate`.
+    /// # Returns
+    /// An `Explanation` with the most-overlapping source sentence and the copied n-grams.
+    pub fn explain(&self, generated: &str) -> Explanation {
+        let words = self.stripped(self.words(generated));
+        let tokens: Vec<Option<u32>> = words
+            .iter()
+            .map(|w| self.tokenizer.to_token_opt(w))
+            .collect();
+
+        let mut best: Option<(usize, &Vec<u32>)> = None;
+        for (sentence, _) in &self.parsed_sentences {
+            let run = Self::longest_common_run(&tokens, sentence);
+            if run > 0 && best.is_none_or(|(longest, _)| run > longest) {
+                best = Some((run, sentence));
+            }
+        }
+
+        let Some((_, sentence)) = best else {
+            return Explanation::default();
+        };
+
+        // a state and the word after it always come from the training data,
+        // so the shortest run worth reporting is one word longer than a state.
+        let min_run = self.chain.order() + 1;
+        let mut copied = Vec::new();
+        let mut covered = 0;
+        for i in 0..tokens.len() {
+            let run = (0..sentence.len())
+                .map(|j| {
+                    tokens[i..]
+                        .iter()
+                        .zip(&sentence[j..])
+                        .take_while(|(a, b)| **a == Some(**b))
+                        .count()
+                })
+                .max()
+                .unwrap_or(0);
+            // a run starting inside the previous one is only reported if it reaches further.
+            if run >= min_run && i + run > covered {
+                covered = i + run;
+                copied.push(
+                    self.join_words(
+                        tokens[i..i + run]
+                            .iter()
+                            .map(|t| self.tokenizer.to_word(t.unwrap_or_default())),
+                        " ",
+                    ),
+                );
+            }
+        }
+
+        Explanation {
+            source: Some(self.join(sentence, " ")),
+            copied,
+        }
+    }
+
+    /// Finds the training sentence sharing the most words with a text, e.g. to tell whether
+    /// a generated text is close to a copy.
+    /// # Arguments
+    /// * `output` - A text, usually one returned by `generate`.
+    /// # Returns
+    /// The most similar sentence and its Jaccard similarity to `output`, the share of distinct
+    /// words in either that appear in both, from 0 to 1. None if no sentence shares a word with it.
+    pub fn most_similar(&self, output: &str) -> Option<(String, f32)> {
+        let mut known: HashSet<u32> = HashSet::new();
+        let mut unknown: HashSet<Cow<str>> = HashSet::new();
+        for word in self.stripped(self.words(output)) {
+            match self.tokenizer.to_token_opt(&word) {
+                Some(token) => known.insert(token),
+                None => unknown.insert(word),
+            };
+        }
+
+        let mut best: Option<(f32, &Vec<u32>)> = None;
+        for (sentence, _) in &self.parsed_sentences {
+            let words: HashSet<u32> = sentence.iter().copied().collect();
+            let shared = words.intersection(&known).count();
+            if shared == 0 {
+                continue;
+            }
+            let union = words.len() + known.len() + unknown.len() - shared;
+            let similarity = shared as f32 / union as f32;
+            if best.is_none_or(|(highest, _)| similarity > highest) {
+                best = Some((similarity, sentence));
+            }
+        }
+
+        best.map(|(similarity, sentence)| (self.join(sentence, " "), similarity))
+    }
+}
+
+/// A one-line summary of the training data and the model,
+/// e.g. `Text{sentences=120, words=800, order=2, states=1234, transitions=5678}`.
+impl<K: Tokenizer> std::fmt::Display for Text<K> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "Text{{sentences={}, words={}, order={}, states={}, transitions={}}}",
+            self.parsed_sentences.len(),
+            self.vocab_size(),
+            self.chain.order(),
+            self.chain.state_count(),
+            self.chain.transition_count()
+        )
+    }
+}
+
+/// Borrowed view of a Text, used by `Text::save`.
+/// The reversed model and the overlap index are not stored, they are rebuilt on first use after loading.
+#[cfg(feature = "serde")]
+#[derive(serde::Serialize)]
+struct TextRef<'a> {
+    reject: Option<&'a str>,
+    parsed_sentences: &'a [(Vec<u32>, i32)],
+    rejoined_text: &'a str,
+    chain: &'a Chain<u32>,
+    tokenizer: &'a Vocab,
+    splitter: SentenceSplitter,
+    split_punctuation: bool,
+    unidecode_input: bool,
+    skip_short_sentences: bool,
+    strip_numbers: bool,
+    number_placeholder: Option<&'a str>,
+}
+
+/// Owned counterpart of `TextRef`, used by `Text::load`.
+#[cfg(feature = "serde")]
+#[derive(serde::Deserialize)]
+struct TextData {
+    reject: Option<String>,
+    parsed_sentences: Vec<(Vec<u32>, i32)>,
+    rejoined_text: String,
+    chain: Chain<u32>,
+    tokenizer: Vocab,
+    splitter: SentenceSplitter,
+    split_punctuation: bool,
+    unidecode_input: bool,
+    skip_short_sentences: bool,
+    #[serde(default)]
+    strip_numbers: bool,
+    #[serde(default)]
+    number_placeholder: Option<String>,
+}
+
+#[cfg(feature = "serde")]
+impl Text {
+    /// Saves the whole model to a file, everything needed to resume generating with `load`.
+    /// The reject pattern is stored as its source and compiled again when loading.
+    /// # Arguments
+    /// * `path` - The file to write, it is replaced if it exists.
+    /// # Returns
+    /// `MarukovError::Io` or `MarukovError::Json` if the model couldn't be written.
+    pub fn save<P: AsRef<std::path::Path>>(&self, path: P) -> Result<(), MarukovError> {
+        let file = std::io::BufWriter::new(std::fs::File::create(path)?);
+        let data = TextRef {
+            reject: self.reject.as_ref().map(Regex::as_str),
+            parsed_sentences: &self.parsed_sentences,
+            rejoined_text: &self.rejoined_text,
+            chain: &self.chain,
+            tokenizer: &self.tokenizer,
+            splitter: self.splitter,
+            split_punctuation: self.split_punctuation,
+            unidecode_input: self.unidecode_input,
+            skip_short_sentences: self.skip_short_sentences,
+            strip_numbers: self.strip_numbers,
+            number_placeholder: self.number_placeholder.as_deref(),
+        };
+        serde_json::to_writer(file, &data)?;
+        Ok(())
+    }
+
+    /// Loads a model written by `save`.
+    /// # Arguments
+    /// * `path` - The file to read.
+    /// # Returns
+    /// The loaded Text, or `MarukovError::Io`, `MarukovError::Json` or
+    /// `MarukovError::InvalidPattern` if the file couldn't be read back.
+    pub fn load<P: AsRef<std::path::Path>>(path: P) -> Result<Self, MarukovError> {
+        let file = std::io::BufReader::new(std::fs::File::open(path)?);
+        let data: TextData = serde_json::from_reader(file)?;
+        Ok(Self {
+            reject: data.reject.as_deref().map(Regex::new).transpose()?,
+            parsed_sentences: data.parsed_sentences,
+            rejoined_text: data.rejoined_text,
+            chain: data.chain,
+            tokenizer: data.tokenizer,
+            splitter: data.splitter,
+            split_punctuation: data.split_punctuation,
+            unidecode_input: data.unidecode_input,
+            skip_short_sentences: data.skip_short_sentences,
+            strip_numbers: data.strip_numbers,
+            number_placeholder: data.number_placeholder,
+            reversed: OnceLock::new(),
+            corpus: OnceLock::new(),
+        })
     }
 }
@@ -0,0 +1,41 @@
+/// Tokenizer splits sentences into words and maps them to the token IDs a `Text` is trained on.
+/// `Vocab` is the default, implement this to plug a different one into `Text::new_with_tokenizer`.
+/// Token IDs don't have to be dense or below `len`, any distinct `u32` per word works.
+pub trait Tokenizer {
+    /// Splits a sentence into words, on whitespace unless overridden.
+    /// Whitespace is any character for which `char::is_whitespace` holds, including non-ASCII
+    /// spaces such as U+3000. Everything else stays inside the word, so zero-width joiners,
+    /// zero-width spaces and combining marks never split a grapheme cluster apart.
+    /// Scripts written without spaces, such as Chinese or Japanese, give one word per run of text.
+    fn split<'a>(&self, sentence: &'a str) -> Vec<&'a str> {
+        sentence.split_whitespace().collect()
+    }
+
+    /// Converts a word to its token ID, adding it if it is not known yet.
+    fn to_token(&mut self, word: &str) -> u32;
+
+    /// Converts a word to its token ID without adding it, returning None if it is not known.
+    fn to_token_opt(&self, word: &str) -> Option<u32>;
+
+    /// Converts a token ID back to its word, returning None if it is not known.
+    fn to_word_opt(&self, token: u32) -> Option<&str>;
+
+    /// Converts a token ID back to its word, unknown IDs yield an empty string.
+    fn to_word(&self, token: u32) -> &str {
+        self.to_word_opt(token).unwrap_or("")
+    }
+
+    /// Reserves a new token that no word maps to, used for the begin and end markers.
+    fn add_special(&mut self, word: &str) -> u32;
+
+    /// Returns the number of tokens, including the ones reserved with `add_special`.
+    fn len(&self) -> usize;
+
+    /// Returns true if there are no tokens.
+    fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Returns the number of tokens reserved with `add_special`.
+    fn special_count(&self) -> usize;
+}
@@ -1,9 +1,33 @@
+use std::borrow::Cow;
 use std::collections::HashMap;
+use std::sync::Arc;
 
-#[derive(Debug)]
+use crate::tokenizer::Tokenizer;
+
+/// How words are normalized before looking them up in a `Vocab`.
+/// Words that normalize to the same key share a token, which is shown in the form it was first seen in.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Normalization {
+    /// Words are kept verbatim.
+    #[default]
+    None,
+    /// Accented and other non-ASCII characters are folded to ASCII, "café" and "cafe" match.
+    /// Characters that have no ASCII form, such as emoji, are kept as they are.
+    #[cfg(feature = "text")]
+    Unidecode,
+    /// Case is ignored, "The" and "the" match.
+    Lowercase,
+}
+
+#[derive(Debug, Clone)]
 pub struct Vocab {
-    word_to_id: HashMap<String, u32>,
-    id_to_word: Vec<String>,
+    // keys share their allocation with `id_to_word` unless case folding changed them.
+    word_to_id: HashMap<Arc<str>, u32>,
+    id_to_word: Vec<Arc<str>>,
+    normalization: Normalization,
+    /// Tokens reserved with `add_special`, which have a word but no key in `word_to_id`.
+    special: Vec<u32>,
 }
 
 impl Default for Vocab {
@@ -18,6 +42,107 @@ impl Vocab {
         Self {
             word_to_id: HashMap::new(),
             id_to_word: Vec::new(),
+            normalization: Normalization::None,
+            special: Vec::new(),
+        }
+    }
+
+    /// Creates a new, empty vocabulary with room for `capacity` words before reallocating.
+    pub fn with_capacity(capacity: usize) -> Self {
+        Self {
+            word_to_id: HashMap::with_capacity(capacity),
+            id_to_word: Vec::with_capacity(capacity),
+            normalization: Normalization::None,
+            special: Vec::new(),
+        }
+    }
+
+    /// Creates a vocabulary from an ordered word list, each word getting its index as token ID,
+    /// e.g. to restore the exact IDs of a saved model.
+    /// A word that appears more than once keeps the ID of its first occurrence when looked up,
+    /// the later copies only keep their index taken so no other ID shifts.
+    pub fn from_words(words: Vec<String>) -> Self {
+        Self::from_parts(words, Normalization::None, Vec::new())
+    }
+
+    /// Rebuilds a vocabulary from its words, skipping the `special` tokens when building the map.
+    fn from_parts(words: Vec<String>, normalization: Normalization, special: Vec<u32>) -> Self {
+        let mut vocab = Self {
+            normalization,
+            special,
+            ..Self::with_capacity(words.len())
+        };
+        for (id, word) in words.into_iter().enumerate() {
+            let word: Arc<str> = Arc::from(word);
+            if !vocab.is_special(id as u32) {
+                vocab
+                    .word_to_id
+                    .entry(vocab.shared_key(&word))
+                    .or_insert(id as u32);
+            }
+            vocab.id_to_word.push(word);
+        }
+        vocab
+    }
+
+    /// Reserves room for at least `additional` more words.
+    pub fn reserve(&mut self, additional: usize) {
+        self.word_to_id.reserve(additional);
+        self.id_to_word.reserve(additional);
+    }
+
+    /// Creates a new, empty vocabulary that ignores case when looking up words.
+    /// "The" and "the" map to the same token, which is shown in the casing it was first seen in.
+    pub fn new_case_insensitive() -> Self {
+        Self::with_normalization(Normalization::Lowercase)
+    }
+
+    /// Creates a new, empty vocabulary that normalizes words before looking them up.
+    pub fn with_normalization(normalization: Normalization) -> Self {
+        Self {
+            normalization,
+            ..Self::new()
+        }
+    }
+
+    /// Returns how words are normalized before looking them up.
+    pub fn normalization(&self) -> Normalization {
+        self.normalization
+    }
+
+    /// Returns the key a word is stored under in `word_to_id`.
+    fn key<'a>(&self, word: &'a str) -> Cow<'a, str> {
+        match self.normalization {
+            Normalization::None => Cow::Borrowed(word),
+            #[cfg(feature = "text")]
+            Normalization::Unidecode if word.is_ascii() => Cow::Borrowed(word),
+            #[cfg(feature = "text")]
+            Normalization::Unidecode => Cow::Owned(Self::fold(word)),
+            Normalization::Lowercase => Cow::Owned(word.to_lowercase()),
+        }
+    }
+
+    /// Transliterates a word to ASCII for `Normalization::Unidecode`.
+    /// Characters without a transliteration, such as emoji, are kept as they are, dropping them
+    /// would give words that only differ in them the same key. Combining marks are dropped,
+    /// so a decomposed "e\u{301}" folds to "e" like the precomposed "é".
+    #[cfg(feature = "text")]
+    fn fold(word: &str) -> String {
+        let mut folded = String::with_capacity(word.len());
+        for c in word.chars() {
+            match unidecode::unidecode_char(c) {
+                "" if !is_combining_mark(c) => folded.push(c),
+                ascii => folded.push_str(ascii),
+            }
+        }
+        folded
+    }
+
+    /// Returns the key a word is stored under, reusing the word's allocation where possible.
+    fn shared_key(&self, word: &Arc<str>) -> Arc<str> {
+        match self.key(word) {
+            Cow::Owned(key) if *key != **word => Arc::from(key),
+            _ => word.clone(),
         }
     }
 
@@ -25,28 +150,174 @@ impl Vocab {
     /// If the word is not present in the vocabulary, it is added.
     /// For retrieving token IDs without adding new words, use `to_token_opt`.
     pub fn to_token(&mut self, word: &str) -> u32 {
-        if let Some(&id) = self.word_to_id.get(word) {
+        let key = self.key(word);
+        if let Some(&id) = self.word_to_id.get(key.as_ref()) {
             return id;
         }
 
         let id = self.id_to_word.len() as u32;
-        self.id_to_word.push(word.to_owned());
-        let inserted = self.id_to_word.last().unwrap().clone();
-        self.word_to_id.insert(inserted, id);
+        let word: Arc<str> = Arc::from(word);
+        self.word_to_id.insert(self.shared_key(&word), id);
+        self.id_to_word.push(word);
 
         id
     }
 
+    /// Reserves a new token for a sentinel such as a begin or end marker.
+    /// No word ever maps to it, not even `word` itself, so input text can't collide with it.
+    /// # Arguments
+    /// * `word` - The word shown for the token by `to_word`.
+    /// # Returns
+    /// The reserved token ID.
+    pub fn add_special(&mut self, word: &str) -> u32 {
+        let id = self.id_to_word.len() as u32;
+        self.id_to_word.push(Arc::from(word));
+        self.special.push(id);
+        id
+    }
+
+    /// Checks whether a token was reserved with `add_special`.
+    pub fn is_special(&self, token: u32) -> bool {
+        self.special.contains(&token)
+    }
+
+    /// Returns the number of tokens reserved with `add_special`.
+    pub fn special_count(&self) -> usize {
+        self.special.len()
+    }
+
     /// Converts a word to its corresponding token ID, returning None if not found.
     pub fn to_token_opt(&self, word: &str) -> Option<u32> {
-        self.word_to_id.get(word).cloned()
+        self.word_to_id.get(self.key(word).as_ref()).cloned()
+    }
+
+    /// Returns the number of distinct words in the vocabulary, including special tokens.
+    pub fn len(&self) -> usize {
+        self.id_to_word.len()
+    }
+
+    /// Returns true if the vocabulary has no words.
+    pub fn is_empty(&self) -> bool {
+        self.id_to_word.is_empty()
+    }
+
+    /// Checks whether a word is in the vocabulary, without adding it.
+    pub fn contains(&self, word: &str) -> bool {
+        self.word_to_id.contains_key(self.key(word).as_ref())
+    }
+
+    /// Adds the words of another vocabulary, e.g. before combining chains trained with separate
+    /// vocabularies. Words already present keep their ID, matched the way `to_token` matches them.
+    /// Special tokens are matched by position, the first special token of `other` maps to
+    /// the first one of `self` and so on, the ones `self` lacks are reserved anew.
+    /// # Arguments
+    /// * `other` - The vocabulary to take the words from.
+    /// # Returns
+    /// A table from every token ID of `other` to the ID of the same word in `self`,
+    /// for rewriting models trained against `other`.
+    pub fn merge(&mut self, other: &Vocab) -> HashMap<u32, u32> {
+        let mut remap = HashMap::with_capacity(other.len());
+        let mut specials = self.special.clone().into_iter();
+        for (id, word) in other.id_to_word.iter().enumerate() {
+            let id = id as u32;
+            let merged = if other.is_special(id) {
+                specials.next().unwrap_or_else(|| self.add_special(word))
+            } else {
+                self.to_token(word)
+            };
+            remap.insert(id, merged);
+        }
+        remap
+    }
+
+    /// Converts a token ID back to its corresponding word, returning None if not found.
+    pub fn to_word_opt(&self, token: u32) -> Option<&str> {
+        self.id_to_word.get(token as usize).map(|s| s.as_ref())
     }
 
     /// Converts a token ID back to its corresponding word.
+    /// Unknown IDs yield an empty string, use `to_word_opt` to tell them apart.
     pub fn to_word(&self, token: u32) -> &str {
-        self.id_to_word
-            .get(token as usize)
-            .map(|s| s.as_str())
-            .unwrap_or("")
+        self.to_word_opt(token).unwrap_or("")
+    }
+}
+
+/// Checks whether a character is in one of the blocks of combining diacritical marks.
+#[cfg(feature = "text")]
+fn is_combining_mark(c: char) -> bool {
+    matches!(
+        c,
+        '\u{0300}'..='\u{036F}'
+            | '\u{1AB0}'..='\u{1AFF}'
+            | '\u{1DC0}'..='\u{1DFF}'
+            | '\u{20D0}'..='\u{20FF}'
+            | '\u{FE20}'..='\u{FE2F}'
+    )
+}
+
+impl Tokenizer for Vocab {
+    fn to_token(&mut self, word: &str) -> u32 {
+        self.to_token(word)
+    }
+
+    fn to_token_opt(&self, word: &str) -> Option<u32> {
+        self.to_token_opt(word)
+    }
+
+    fn to_word_opt(&self, token: u32) -> Option<&str> {
+        self.to_word_opt(token)
+    }
+
+    fn add_special(&mut self, word: &str) -> u32 {
+        self.add_special(word)
+    }
+
+    fn len(&self) -> usize {
+        self.len()
+    }
+
+    fn special_count(&self) -> usize {
+        self.special_count()
+    }
+}
+
+/// Serialized form of a vocabulary.
+/// Only the words are stored, as the index of each word is its token ID.
+#[cfg(feature = "serde")]
+#[derive(serde::Serialize, serde::Deserialize)]
+struct VocabData<W> {
+    words: W,
+    #[serde(default)]
+    normalization: Normalization,
+    #[serde(default)]
+    special: Vec<u32>,
+}
+
+#[cfg(feature = "serde")]
+impl serde::Serialize for Vocab {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        VocabData {
+            words: self
+                .id_to_word
+                .iter()
+                .map(|w| w.as_ref())
+                .collect::<Vec<&str>>(),
+            normalization: self.normalization,
+            special: self.special.clone(),
+        }
+        .serialize(serializer)
+    }
+}
+
+/// `word_to_id` is rebuilt from the stored words.
+#[cfg(feature = "serde")]
+impl<'de> serde::Deserialize<'de> for Vocab {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let data = VocabData::<Vec<String>>::deserialize(deserializer)?;
+        Ok(Self::from_parts(
+            data.words,
+            data.normalization,
+            data.special,
+        ))
     }
 }
@@ -0,0 +1,419 @@
+use marukov::{BeginStrategy, Chain};
+
+fn runs(runs: &[&str]) -> Vec<Vec<String>> {
+    runs.iter()
+        .map(|run| run.split(' ').map(str::to_string).collect())
+        .collect()
+}
+
+fn chain(data: &[&str]) -> Chain<String> {
+    Chain::new(&runs(data), "<s>".to_string(), "</s>".to_string(), 1)
+}
+
+#[test]
+fn chains_with_different_begin_weights_are_not_equal() {
+    let a = chain(&["a b", "a b", "c b"]);
+    let b = chain(&["a b", "c b", "c b"]);
+    assert_ne!(a, b);
+    assert_eq!(a, chain(&["c b", "a b", "a b"]));
+}
+
+#[test]
+fn chains_with_different_begin_settings_are_not_equal() {
+    let a = chain(&["a b", "a b", "c b"]);
+    assert_ne!(a, a.clone().with_begin_strategy(BeginStrategy::Uniform));
+    assert_ne!(a, a.clone().with_min_begin_weight(2));
+    assert_eq!(a, a.clone().with_precomputed());
+}
+
+#[test]
+fn recompute_drops_nonpositive_counts() {
+    let mut a = chain(&["a b", "c b"]);
+    let begin = vec!["<s>".to_string()];
+    a.model_mut()
+        .get_mut(&begin)
+        .unwrap()
+        .insert("c".to_string(), 0);
+    a.recompute();
+    assert!(!a.successors(&begin).unwrap().contains_key("c"));
+    for _ in 0..50 {
+        assert_eq!(a.generate(None), vec!["a", "b"]);
+    }
+}
+
+#[cfg(feature = "serde")]
+#[test]
+fn markovify_import_rejects_nonpositive_counts() {
+    for count in [0, -1] {
+        let json = format!(r#"[[["___BEGIN__"], {{"a": 2, "b": {count}}}]]"#);
+        assert!(Chain::from_markovify_json(&json).is_err());
+    }
+    assert!(Chain::from_markovify_json(r#"[[["___BEGIN__"], {"a": 2}]]"#).is_ok());
+}
+
+#[test]
+fn zero_temperature_breaks_ties_like_generate_greedy() {
+    use marukov::ChainOptions;
+    use rand::SeedableRng;
+    use rand::rngs::StdRng;
+
+    let a = chain(&["x b", "y b", "z b", "y c", "x c"]);
+    let options = ChainOptions {
+        temperature: 0.0,
+        ..ChainOptions::default()
+    };
+    let mut rng = StdRng::seed_from_u64(0);
+    assert_eq!(a.generate_greedy(None), vec!["x", "b"]);
+    assert_eq!(
+        a.generate_with_options(None, &options, &mut rng),
+        vec!["x", "b"]
+    );
+    let shaped = ChainOptions {
+        target_tokens: Some(2),
+        ..options
+    };
+    assert_eq!(
+        a.generate_with_options(None, &shaped, &mut rng),
+        vec!["x", "b"]
+    );
+}
+
+#[test]
+fn transition_count_counts_distinct_transitions() {
+    let a = chain(&["a b", "a b", "a c"]);
+    // <s> -> a, a -> b, a -> c, b -> </s>, c -> </s>
+    assert_eq!(a.transition_count(), 5);
+    assert_eq!(a.state_count(), 4);
+}
+
+#[test]
+fn endless_model_stops_at_the_step_cap() {
+    // every state only leads back to "a", so the end token is never reached.
+    let mut a = Chain::default("<s>".to_string(), "</s>".to_string(), 1).with_max_steps(50);
+    for state in ["<s>", "a"] {
+        a.model_mut()
+            .entry(vec![state.to_string()])
+            .or_default()
+            .insert("a".to_string(), 1);
+    }
+    a.recompute();
+
+    let generated = a.generate(None);
+    assert_eq!(generated.len(), 50);
+    assert!(generated.iter().all(|word| word == "a"));
+    assert_eq!(a.iter(None).count(), 50);
+    assert_eq!(a.generate_greedy(None).len(), 50);
+}
+
+#[test]
+fn combined_counts_beyond_i32_still_sample_every_choice() {
+    use rand::SeedableRng;
+    use rand::rngs::StdRng;
+    use std::collections::HashSet;
+
+    let a = chain(&["a b"]);
+    let c = chain(&["c b"]);
+    let weight = 2e9;
+    // the begin state's counts add up to 4e9, past `i32::MAX`.
+    let combined = Chain::combine(&[&a, &c], &[weight, weight]).unwrap();
+    let mut rng = StdRng::seed_from_u64(0);
+    let firsts: HashSet<String> = (0..100)
+        .map(|_| combined.generate_with_rng(None, &mut rng)[0].clone())
+        .collect();
+    assert_eq!(firsts, HashSet::from(["a".to_string(), "c".to_string()]));
+
+    // merging the same transition twice saturates instead of wrapping around.
+    let doubled = Chain::combine(&[&a, &a], &[weight, weight]).unwrap();
+    let begin = vec!["<s>".to_string()];
+    assert_eq!(doubled.successors(&begin).unwrap()["a"], i32::MAX);
+    assert_eq!(doubled.generate_with_rng(None, &mut rng), vec!["a", "b"]);
+}
+
+#[cfg(feature = "serde")]
+#[test]
+fn markovify_json_round_trips() {
+    // the shape of markovify's `Text.to_json()`, with the chain nested as a JSON string.
+    let chain_json = r#"[[["___BEGIN__", "___BEGIN__"], {"the": 2}], [["___BEGIN__", "the"], {"cat": 1, "dog": 1}], [["the", "cat"], {"___END__": 1}], [["the", "dog"], {"___END__": 1}]]"#;
+    let text_json = serde_json::json!({ "state_size": 2, "chain": chain_json }).to_string();
+    let imported = Chain::from_markovify_json(&text_json).unwrap();
+    assert_eq!(imported.order(), 2);
+    for _ in 0..20 {
+        let generated = imported.generate(None);
+        assert!(generated == ["the", "cat"] || generated == ["the", "dog"]);
+    }
+
+    let exported = imported.to_markovify_json();
+    assert_eq!(Chain::from_markovify_json(&exported).unwrap(), imported);
+    assert_eq!(Chain::from_markovify_json(chain_json).unwrap(), imported);
+}
+
+#[test]
+fn low_temperature_with_large_counts_still_generates() {
+    use marukov::ChainOptions;
+    use rand::SeedableRng;
+    use rand::rngs::StdRng;
+
+    let mut runs = vec![vec![1u32, 2, 3]; 2000];
+    runs.push(vec![1, 4]);
+    let a = Chain::new(&runs, 0, u32::MAX, 1);
+    let options = ChainOptions {
+        temperature: 0.01,
+        ..ChainOptions::default()
+    };
+    let mut rng = StdRng::seed_from_u64(0);
+    assert_eq!(
+        a.generate_with_options(None, &options, &mut rng),
+        vec![1, 2, 3]
+    );
+    let shaped = ChainOptions {
+        target_tokens: Some(3),
+        ..options
+    };
+    assert_eq!(
+        a.generate_with_options(None, &shaped, &mut rng),
+        vec![1, 2, 3]
+    );
+}
+
+#[test]
+fn min_tokens_applies_at_the_first_step() {
+    use marukov::ChainOptions;
+    use rand::SeedableRng;
+    use rand::rngs::StdRng;
+
+    let runs: Vec<Vec<u32>> = vec![vec![], vec![], vec![], vec![], vec![1, 2], vec![3, 4]];
+    let a = Chain::new(&runs, 0, u32::MAX, 1);
+    let options = ChainOptions {
+        min_tokens: 2,
+        ..ChainOptions::default()
+    };
+    let mut rng = StdRng::seed_from_u64(0);
+    for _ in 0..100 {
+        assert_eq!(a.generate_with_options(None, &options, &mut rng).len(), 2);
+    }
+    let uniform = a.with_begin_strategy(BeginStrategy::Uniform);
+    for _ in 0..100 {
+        assert_eq!(
+            uniform
+                .generate_with_options(None, &options, &mut rng)
+                .len(),
+            2
+        );
+    }
+}
+
+#[test]
+fn cleared_chain_scores_like_a_fresh_one() {
+    use marukov::Smoothing;
+
+    let smoothing = Smoothing::AddK(1.0);
+    let mut cleared =
+        Chain::new(&[vec![1u32, 2, 3], vec![2, 3]], 0, u32::MAX, 1).with_smoothing(smoothing);
+    cleared.clear();
+    let fresh = Chain::default(0, u32::MAX, 1).with_smoothing(smoothing);
+    assert_eq!(cleared, fresh);
+    assert_eq!(
+        cleared.sequence_log_prob(&[1]),
+        fresh.sequence_log_prob(&[1])
+    );
+}
+
+#[test]
+fn edge_list_has_one_edge_per_transition() {
+    let a = chain(&["a b c", "a c", "b c a"]);
+    let edges = a.to_edge_list();
+    assert_eq!(edges.len(), a.transition_count());
+    let total: i32 = edges.iter().map(|(_, _, count)| count).sum();
+    // one transition per token plus one into the end token for every run.
+    assert_eq!(total, 8 + 3);
+}
+
+#[test]
+fn lower_orders_generate_more_varied_output() {
+    use rand::SeedableRng;
+    use rand::rngs::StdRng;
+    use std::collections::HashSet;
+
+    let corpus = ["a b c d", "d c b a", "b d a c", "c a d b"];
+    let data = runs(&corpus);
+    let distinct = |order: usize| -> HashSet<Vec<String>> {
+        let chain = Chain::new(&data, "<s>".to_string(), "</s>".to_string(), order);
+        let mut rng = StdRng::seed_from_u64(0);
+        (0..200)
+            .map(|_| chain.generate_with_rng(None, &mut rng))
+            .collect()
+    };
+
+    let order1 = distinct(1);
+    let order3 = distinct(3);
+    // every state of three words is unique to one run, so order 3 can only copy them.
+    assert!(order3.iter().all(|generated| data.contains(generated)));
+    assert!(order1.len() > order3.len());
+    assert_eq!(
+        Chain::new(&data, "<s>".to_string(), "</s>".to_string(), 3)
+            .iter(None)
+            .state()
+            .len(),
+        3
+    );
+}
+
+#[cfg(feature = "serde")]
+#[test]
+fn serde_round_trip_generates_the_same() {
+    use rand::SeedableRng;
+    use rand::rngs::StdRng;
+
+    let a = chain(&["the cat sat", "the dog sat", "a cat ran", "the dog ran far"]);
+    let json = serde_json::to_string(&a).unwrap();
+    let loaded: Chain<String> = serde_json::from_str(&json).unwrap();
+    assert_eq!(loaded, a);
+
+    let mut rng = StdRng::seed_from_u64(7);
+    let mut loaded_rng = StdRng::seed_from_u64(7);
+    for _ in 0..50 {
+        assert_eq!(
+            a.generate_with_rng(None, &mut rng),
+            loaded.generate_with_rng(None, &mut loaded_rng)
+        );
+    }
+}
+
+#[test]
+fn same_seed_generates_the_same() {
+    use rand::SeedableRng;
+    use rand::rngs::StdRng;
+
+    let a = chain(&["a b c", "a c b", "b a c", "c a b", "b c a"]);
+    let first: Vec<Vec<String>> = {
+        let mut rng = StdRng::seed_from_u64(42);
+        (0..20)
+            .map(|_| a.generate_with_rng(None, &mut rng))
+            .collect()
+    };
+    let second: Vec<Vec<String>> = {
+        let mut rng = StdRng::seed_from_u64(42);
+        (0..20)
+            .map(|_| a.generate_with_rng(None, &mut rng))
+            .collect()
+    };
+    assert_eq!(first, second);
+}
+
+#[test]
+fn unknown_init_state_generates_nothing() {
+    let a = chain(&["a b c"]);
+    let bogus = vec!["nowhere".to_string()];
+    assert_eq!(a.next(&bogus), None);
+    assert!(a.generate(Some(bogus.clone())).is_empty());
+    assert_eq!(a.iter(Some(bogus)).count(), 0);
+    // a state the model knows continues from there.
+    assert_eq!(a.generate(Some(vec!["b".to_string()])), vec!["c"]);
+}
+
+#[cfg(feature = "rayon")]
+#[test]
+fn parallel_build_matches_sequential_build() {
+    let data: Vec<Vec<String>> = (0..500)
+        .map(|i| {
+            format!("w{} w{} w{} w{}", i % 7, i % 11, i % 13, i % 5)
+                .split(' ')
+                .map(str::to_string)
+                .collect()
+        })
+        .collect();
+    for order in 1..=3 {
+        let sequential = Chain::new(&data, "<s>".to_string(), "</s>".to_string(), order);
+        let parallel = Chain::new_parallel(&data, "<s>".to_string(), "</s>".to_string(), order);
+        assert_eq!(parallel, sequential);
+        let sorted = |chain: &Chain<String>| {
+            let mut edges = chain.to_edge_list();
+            edges.sort();
+            edges
+        };
+        assert_eq!(sorted(&parallel), sorted(&sequential));
+    }
+}
+
+#[test]
+fn fixed_chain_generates_like_chain() {
+    use marukov::{DefaultFixedChain, FixedChain};
+    use rand::SeedableRng;
+    use rand::rngs::StdRng;
+
+    let data = runs(&[
+        "the cat sat on the mat",
+        "the dog sat on the rug",
+        "a cat ran",
+    ]);
+    let (begin, end) = ("<s>".to_string(), "</s>".to_string());
+    let dynamic = Chain::new(&data, begin.clone(), end.clone(), 2);
+    let fixed: DefaultFixedChain<String> = FixedChain::new(&data, begin.clone(), end.clone());
+    let single = Chain::new(&data, begin.clone(), end.clone(), 1);
+    let fixed_single: FixedChain<String, 1> = FixedChain::new(&data, begin, end);
+    assert_eq!(fixed.state_count(), dynamic.state_count());
+
+    let mut rng = StdRng::seed_from_u64(3);
+    let mut fixed_rng = StdRng::seed_from_u64(3);
+    for _ in 0..50 {
+        assert_eq!(
+            fixed.generate_with_rng(None, &mut fixed_rng),
+            dynamic.generate_with_rng(None, &mut rng)
+        );
+        assert_eq!(
+            fixed_single.generate_with_rng(None, &mut fixed_rng),
+            single.generate_with_rng(None, &mut rng)
+        );
+    }
+}
+
+#[cfg(feature = "serde")]
+#[test]
+fn serialized_chains_are_byte_identical() {
+    let data = ["the cat sat", "the dog sat", "a cat ran", "the dog ran far"];
+    let first = serde_json::to_string(&chain(&data)).unwrap();
+    let second = serde_json::to_string(&chain(&data)).unwrap();
+    assert_eq!(first, second);
+
+    // saving what was loaded writes the same bytes again.
+    let loaded: Chain<String> = serde_json::from_str(&first).unwrap();
+    assert_eq!(serde_json::to_string(&loaded).unwrap(), first);
+    let precomputed = chain(&data).with_precomputed();
+    let saved = serde_json::to_string(&precomputed).unwrap();
+    let reloaded: Chain<String> = serde_json::from_str(&saved).unwrap();
+    assert_eq!(serde_json::to_string(&reloaded).unwrap(), saved);
+}
+
+#[test]
+fn back_off_follows_states_sharing_a_suffix() {
+    use marukov::ChainOptions;
+    use rand::SeedableRng;
+    use rand::rngs::StdRng;
+    use std::collections::HashSet;
+
+    let mut a = Chain::new(&runs(&["x b c"]), "<s>".to_string(), "</s>".to_string(), 2);
+    let options = ChainOptions {
+        back_off: true,
+        ..ChainOptions::default()
+    };
+    let unseen = vec!["q".to_string(), "b".to_string()];
+    let mut rng = StdRng::seed_from_u64(0);
+    assert!(
+        a.generate_with_rng(Some(unseen.clone()), &mut rng)
+            .is_empty()
+    );
+    assert_eq!(
+        a.generate_with_options(Some(unseen.clone()), &options, &mut rng),
+        vec!["c"]
+    );
+
+    // the suffix index is rebuilt once the model changes.
+    a.add_run(&["y", "b", "d"].map(str::to_string));
+    let seen: HashSet<Vec<String>> = (0..50)
+        .map(|_| a.generate_with_options(Some(unseen.clone()), &options, &mut rng))
+        .collect();
+    assert_eq!(
+        seen,
+        HashSet::from([vec!["c".to_string()], vec!["d".to_string()]])
+    );
+}
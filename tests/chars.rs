@@ -0,0 +1,20 @@
+use marukov::{CharOptions, CharText};
+
+#[test]
+fn repeated_words_are_trained_on_every_time() {
+    let text = CharText::with_order("ab\nab\nab\nac".to_string(), 1);
+    let successors = text.chain().successors(&vec!['a']).unwrap();
+    assert_eq!(successors[&'b'], 3);
+    assert_eq!(successors[&'c'], 1);
+}
+
+#[test]
+fn unbounded_max_chars_does_not_overflow() {
+    let text = CharText::with_order("abc\nabd\nabe".to_string(), 1);
+    let options = CharOptions {
+        max_chars: usize::MAX,
+        allow_copies: true,
+        ..CharOptions::default()
+    };
+    assert!(text.generate(options).is_ok());
+}
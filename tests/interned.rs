@@ -0,0 +1,13 @@
+use marukov::Interner;
+
+#[test]
+fn interned_values_resolve_to_the_same_id() {
+    let mut interner = Interner::new();
+    let a = interner.intern(&"a".to_string());
+    let b = interner.intern(&"b".to_string());
+    assert_eq!(interner.intern(&"a".to_string()), a);
+    assert_ne!(a, b);
+    assert_eq!(interner.get(&"b".to_string()), Some(b));
+    assert_eq!(interner.resolve(a).map(String::as_str), Some("a"));
+    assert_eq!(interner.resolve(2), None);
+}
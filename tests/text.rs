@@ -0,0 +1,458 @@
+#![cfg(feature = "text")]
+
+use marukov::{Normalization, Smoothing, Text, TextBuilder, TextOptions, Tokenizer};
+
+fn unverified() -> TextOptions {
+    TextOptions {
+        verify: false,
+        ..TextOptions::default()
+    }
+}
+
+#[test]
+fn weighted_sentences_skip_nonpositive_weights_everywhere() {
+    let text = Text::from_weighted_sentences([
+        ("the quick fox ends here".to_string(), 5),
+        ("the slow fox ends here".to_string(), 0),
+    ]);
+
+    for seed in 0..50 {
+        let forward = text.generate_seeded(unverified(), seed).unwrap();
+        assert!(!forward.contains("slow"), "{forward}");
+        let backward = text
+            .generate_ending_with("ends here", unverified())
+            .unwrap();
+        assert!(!backward.contains("slow"), "{backward}");
+    }
+    assert_eq!(text.token_count(), 25);
+    assert!(
+        text.word_frequencies()
+            .iter()
+            .all(|(word, _)| word != "slow")
+    );
+}
+
+#[test]
+fn explain_reports_runs_copied_from_the_source() {
+    let text = Text::new(
+        "the cat sat on the warm mat today\nmy dog ran under the big red car".to_string(),
+    );
+
+    let explanation = text.explain("the cat sat on the big red car");
+    assert_eq!(
+        explanation.source.as_deref(),
+        Some("the cat sat on the warm mat today")
+    );
+    assert_eq!(explanation.copied, vec!["the cat sat on the"]);
+
+    let explanation = text.explain("nothing in common");
+    assert!(explanation.source.is_none());
+    assert!(explanation.copied.is_empty());
+}
+
+#[test]
+fn explain_finds_copied_runs_in_accepted_output() {
+    let text = Text::new(
+        [
+            "the old man walked slowly down the long dusty road to town",
+            "a young girl walked slowly across the wide green field at dawn",
+            "the old dog slept quietly near the warm kitchen fire all night",
+            "a tired man slept quietly in the long dusty wagon after work",
+        ]
+        .join("\n"),
+    );
+    let options = TextOptions {
+        max_overlap_ratio: 0.6,
+        ..TextOptions::default()
+    };
+
+    let mut reported = 0;
+    for seed in 0..200 {
+        if let Ok(generated) = text.generate_seeded(options.clone(), seed) {
+            reported += !text.explain(&generated).copied.is_empty() as usize;
+        }
+    }
+    assert!(reported > 0);
+}
+
+#[test]
+fn stripped_numbers_are_stripped_from_scored_input() {
+    let data = "I was born in 1999 in a small town.\nShe moved in 2004 to a big city.".to_string();
+    for placeholder in [Some("<num>"), None] {
+        let text = TextBuilder::new()
+            .strip_numbers(true)
+            .number_placeholder(placeholder)
+            .build(data.clone());
+        let sentence = "I was born in 1999 in a small town.";
+
+        assert!(text.tokenize_context("born in 1999").is_some());
+        assert!(text.score(sentence).is_some());
+        assert!(text.perplexity(sentence).is_some());
+        assert!(
+            text.generate_with_start("born in 1999", unverified())
+                .is_ok()
+        );
+    }
+}
+
+#[test]
+fn overlap_check_does_not_match_across_sentences() {
+    let text = Text::from_sentences(["one two three".to_string(), "four five six".to_string()]);
+    let words = |words: &[&str]| -> Vec<String> { words.iter().map(|w| w.to_string()).collect() };
+
+    // "three four" only appears where the two sentences were joined.
+    assert!(text.check_overlap(&words(&["three", "four"]), 0.5, 15));
+    assert!(!text.check_overlap(&words(&["two", "three"]), 0.5, 15));
+    assert!(!text.check_overlap(&words(&["four", "five"]), 0.5, 15));
+}
+
+#[cfg(feature = "serde")]
+#[test]
+fn saved_text_generates_the_same_after_loading() {
+    let text = TextBuilder::new().build(
+        "The cat sat on the mat. The dog sat on the rug. A cat ran under the old bed. \
+         The dog ran over the mat. A bird sat on the old fence."
+            .to_string(),
+    );
+    let path = std::env::temp_dir().join(format!("marukov-save-{}.json", std::process::id()));
+    text.save(&path).unwrap();
+    let loaded = Text::load(&path).unwrap();
+    std::fs::remove_file(&path).unwrap();
+
+    for seed in 0..20 {
+        assert_eq!(
+            text.generate_seeded(unverified(), seed).unwrap(),
+            loaded.generate_seeded(unverified(), seed).unwrap()
+        );
+        assert_eq!(
+            text.generate_seeded(TextOptions::default(), seed).ok(),
+            loaded.generate_seeded(TextOptions::default(), seed).ok()
+        );
+    }
+    assert_eq!(text.word_frequencies(), loaded.word_frequencies());
+}
+
+/// Hands out IDs 1000 apart, so none of them are below `len`.
+#[derive(Default)]
+struct SparseTokenizer {
+    words: Vec<String>,
+    specials: usize,
+}
+
+impl SparseTokenizer {
+    fn id(index: usize) -> u32 {
+        index as u32 * 1000 + 7
+    }
+}
+
+impl Tokenizer for SparseTokenizer {
+    fn to_token(&mut self, word: &str) -> u32 {
+        self.to_token_opt(word).unwrap_or_else(|| {
+            self.words.push(word.to_string());
+            Self::id(self.words.len() - 1)
+        })
+    }
+
+    fn to_token_opt(&self, word: &str) -> Option<u32> {
+        self.words
+            .iter()
+            .skip(self.specials)
+            .position(|w| w == word)
+            .map(|index| Self::id(index + self.specials))
+    }
+
+    fn to_word_opt(&self, token: u32) -> Option<&str> {
+        let index = token.checked_sub(7)?;
+        if index % 1000 != 0 {
+            return None;
+        }
+        self.words.get(index as usize / 1000).map(String::as_str)
+    }
+
+    fn add_special(&mut self, word: &str) -> u32 {
+        self.words.push(word.to_string());
+        self.specials += 1;
+        Self::id(self.words.len() - 1)
+    }
+
+    fn len(&self) -> usize {
+        self.words.len()
+    }
+
+    fn special_count(&self) -> usize {
+        self.specials
+    }
+}
+
+#[test]
+fn sparse_token_ids_are_counted_and_scored() {
+    let data = "the cat sat\nthe dog sat\nthe cat ran".to_string();
+    let text = TextBuilder::new()
+        .smoothing(Smoothing::AddK(1.0))
+        .build_with_tokenizer(data, SparseTokenizer::default());
+
+    let frequencies = text.word_frequencies();
+    assert_eq!(frequencies[0], ("the".to_string(), 3));
+    assert_eq!(frequencies.len(), 5);
+    assert!(text.score("the bird sat").is_some());
+    assert!(text.score("the bird sat") < text.score("the cat sat"));
+}
+
+fn words_of(text: &Text) -> Vec<String> {
+    let mut words: Vec<String> = text
+        .word_frequencies()
+        .into_iter()
+        .map(|(w, _)| w)
+        .collect();
+    words.sort();
+    words
+}
+
+#[test]
+fn cjk_text_splits_on_unicode_whitespace_only() {
+    let text = Text::from_sentences(["今日は\u{3000}晴れ です".to_string()]);
+    assert_eq!(words_of(&text), ["です", "今日は", "晴れ"]);
+    assert_eq!(
+        text.tokenize_context("今日は\u{3000}晴れ").map(|t| t.len()),
+        Some(2)
+    );
+}
+
+#[test]
+fn emoji_zwj_sequences_stay_one_word() {
+    let family = "\u{1F468}\u{200D}\u{1F469}\u{200D}\u{1F467}";
+    let text = Text::from_sentences([format!("my {family} is here")]);
+    assert!(words_of(&text).contains(&family.to_string()));
+    assert_eq!(
+        text.tokenize_context(&format!("my {family}"))
+            .map(|t| t.len()),
+        Some(2)
+    );
+    assert_eq!(text.tokenize_context("\u{1F468}"), None);
+
+    // unidecode has no ASCII form for emoji, so they are kept apart instead of dropped.
+    let folded = TextBuilder::new()
+        .normalization(Normalization::Unidecode)
+        .build_from_sentences([
+            format!("my {family} is here"),
+            "my \u{1F436} is here".to_string(),
+        ]);
+    assert_ne!(
+        folded.tokenize_context(family),
+        folded.tokenize_context("\u{1F436}")
+    );
+}
+
+#[test]
+fn combining_diacritics_stay_on_their_letter() {
+    let decomposed = "cafe\u{301}";
+    let text = Text::from_sentences([format!("a {decomposed} au lait")]);
+    assert!(words_of(&text).contains(&decomposed.to_string()));
+    assert_eq!(text.tokenize_context("caf\u{e9}"), None);
+
+    // folding to ASCII makes the decomposed and precomposed forms the same word.
+    let folded = TextBuilder::new()
+        .normalization(Normalization::Unidecode)
+        .build_from_sentences([format!("a {decomposed} au lait")]);
+    assert_eq!(
+        folded.tokenize_context("caf\u{e9}"),
+        folded.tokenize_context(decomposed)
+    );
+    assert!(folded.tokenize_context("caf\u{e9}").is_some());
+}
+
+#[test]
+fn text_edge_list_decodes_words() {
+    let text = Text::from_sentences(["the cat sat".to_string(), "the dog sat".to_string()]);
+    let edges = text.to_edge_list();
+    // the, cat/dog after "the", sat after each of them, and the two ends of sentence.
+    assert_eq!(edges.len(), 7);
+    assert!(edges.iter().any(
+        |(state, next, count)| state.last().is_some_and(|w| w == "the")
+            && next == "cat"
+            && *count == 1
+    ));
+}
+
+#[test]
+fn cold_start_varies_the_first_word_of_a_single_starter_corpus() {
+    let text = Text::from_sentences([
+        "the cat sat on the mat".to_string(),
+        "the dog ran to the park".to_string(),
+        "the bird flew over the house".to_string(),
+    ]);
+    let first_words = |options: TextOptions| -> Vec<String> {
+        (0..50)
+            .filter_map(|seed| text.generate_seeded(options.clone(), seed).ok())
+            .map(|sentence| sentence.split(' ').next().unwrap().to_string())
+            .collect()
+    };
+
+    assert!(first_words(unverified()).iter().all(|word| word == "the"));
+    let cold = TextOptions {
+        cold_start_threshold: 2,
+        ..unverified()
+    };
+    assert!(first_words(cold).iter().any(|word| word != "the"));
+}
+
+#[test]
+fn same_seed_generates_the_same_text() {
+    let text = Text::from_sentences([
+        "the cat sat on the mat".to_string(),
+        "the dog sat on the rug".to_string(),
+        "a cat ran over the rug".to_string(),
+    ]);
+    for seed in 0..20 {
+        assert_eq!(
+            text.generate_seeded(unverified(), seed),
+            text.generate_seeded(unverified(), seed)
+        );
+    }
+}
+
+#[test]
+fn each_generate_error_has_its_cause() {
+    use marukov::GenerateError;
+    use std::time::Duration;
+
+    let empty = Text::from_sentences(Vec::<String>::new());
+    assert_eq!(empty.generate(unverified()), Err(GenerateError::EmptyModel));
+
+    let text = Text::from_sentences(["the only sentence there is".to_string()]);
+    let no_words = TextOptions {
+        max_words: 0,
+        ..unverified()
+    };
+    assert_eq!(
+        text.generate(no_words),
+        Err(GenerateError::ConstraintsUnsatisfiable)
+    );
+
+    // a single sentence can only be copied, which the overlap check never accepts.
+    let strict = TextOptions {
+        relax_on_failure: false,
+        tries: 10,
+        ..TextOptions::default()
+    };
+    assert_eq!(text.generate(strict), Err(GenerateError::ExhaustedTries));
+
+    let no_time = TextOptions {
+        time_budget: Some(Duration::ZERO),
+        ..TextOptions::default()
+    };
+    assert_eq!(text.generate(no_time), Err(GenerateError::Timeout));
+
+    assert_eq!(
+        text.generate_with_start("zebra", unverified()),
+        Err(GenerateError::UnknownStart)
+    );
+}
+
+#[test]
+fn punctuation_splitting_finds_every_sentence_on_a_line() {
+    use marukov::SentenceSplitter;
+
+    let line = "The cat sat. A dog ran! Is it here?";
+    assert_eq!(
+        SentenceSplitter::Punctuation.split(line),
+        ["The cat sat.", "A dog ran!", "Is it here?"]
+    );
+
+    let text = TextBuilder::new()
+        .splitter(SentenceSplitter::Punctuation)
+        .build(line.to_string());
+    for start in ["The", "A", "Is"] {
+        assert!(
+            text.ngram_count(&[start]) > 0,
+            "{start} should start a sentence"
+        );
+    }
+    let lines = TextBuilder::new().build(line.to_string());
+    assert_eq!(lines.ngram_count(&["A"]), 0);
+}
+
+#[test]
+fn bracketed_lines_are_kept_without_a_reject_pattern() {
+    let data = "keep this (bracketed) line\nplain line here".to_string();
+    let default = Text::new(data.clone());
+    assert_eq!(default.ngram_count(&["keep"]), 0);
+    assert!(default.ngram_count(&["plain"]) > 0);
+
+    let unfiltered = Text::with_reject(data, None);
+    assert!(unfiltered.ngram_count(&["keep"]) > 0);
+    assert!(unfiltered.ngram_count(&["plain"]) > 0);
+}
+
+#[test]
+fn each_marukov_error_comes_from_its_cause() {
+    use marukov::{Chain, CombineError, GenerateError, MarukovError};
+
+    let invalid = Text::with_reject_pattern("a line".to_string(), "(unclosed");
+    assert!(matches!(invalid, Err(MarukovError::InvalidPattern(_))));
+
+    let generate = || -> Result<String, MarukovError> {
+        Ok(Text::from_sentences(Vec::<String>::new()).generate(unverified())?)
+    };
+    assert!(matches!(
+        generate(),
+        Err(MarukovError::Generate(GenerateError::EmptyModel))
+    ));
+
+    let combine = || -> Result<Chain<u32>, MarukovError> { Ok(Chain::combine(&[], &[])?) };
+    assert!(matches!(
+        combine(),
+        Err(MarukovError::Combine(CombineError::Empty))
+    ));
+}
+
+#[cfg(feature = "serde")]
+#[test]
+fn loading_a_missing_or_broken_file_fails_with_its_cause() {
+    use marukov::MarukovError;
+
+    let dir = std::env::temp_dir();
+    let missing = dir.join(format!("marukov-missing-{}.json", std::process::id()));
+    assert!(matches!(Text::load(&missing), Err(MarukovError::Io(_))));
+
+    let broken = dir.join(format!("marukov-broken-{}.json", std::process::id()));
+    std::fs::write(&broken, "not json").unwrap();
+    let loaded = Text::load(&broken);
+    std::fs::remove_file(&broken).unwrap();
+    assert!(matches!(loaded, Err(MarukovError::Json(_))));
+}
+
+#[test]
+fn overlap_check_decides_like_a_plain_substring_search() {
+    let sentences = [
+        "the cat sat on the mat",
+        "the dog sat on the rug",
+        "a cat ran over the old rug",
+        "the old dog ran on the mat",
+    ];
+    let text = Text::from_sentences(sentences.map(str::to_string));
+    let rejoined = sentences.join("\n");
+    // the check before the suffix automaton, searching the rejoined sentences for every gram.
+    let contains_check = |words: &[String], ratio: f32, total: usize| -> bool {
+        let overlap_max = total.min((ratio * words.len() as f32).round() as usize);
+        let gram_count = words.len().saturating_sub(overlap_max).max(1);
+        (0..gram_count).all(|i| {
+            let end = (i + overlap_max + 1).min(words.len());
+            !rejoined.contains(&words[i..end].join(" "))
+        })
+    };
+
+    for seed in 0..300 {
+        let Ok(generated) = text.generate_seeded(unverified(), seed) else {
+            continue;
+        };
+        let words: Vec<String> = generated.split(' ').map(str::to_string).collect();
+        for (ratio, total) in [(0.7, 15), (0.5, 3), (0.3, 2), (1.0, 1)] {
+            assert_eq!(
+                text.check_overlap(&words, ratio, total),
+                contains_check(&words, ratio, total),
+                "{generated:?}"
+            );
+        }
+    }
+}
@@ -0,0 +1,50 @@
+use marukov::Vocab;
+
+#[cfg(feature = "serde")]
+#[test]
+fn vocab_round_trips_through_serde() {
+    let mut vocab = Vocab::new();
+    let begin = vocab.add_special("<s>");
+    let ids: Vec<u32> = ["the", "cat", "sat"]
+        .iter()
+        .map(|w| vocab.to_token(w))
+        .collect();
+
+    let json = serde_json::to_string(&vocab).unwrap();
+    let mut loaded: Vocab = serde_json::from_str(&json).unwrap();
+    for (word, id) in ["the", "cat", "sat"].iter().zip(&ids) {
+        assert_eq!(loaded.to_token_opt(word), Some(*id));
+        assert_eq!(loaded.to_word(*id), *word);
+    }
+    assert_eq!(loaded.len(), vocab.len());
+    assert!(loaded.is_special(begin));
+    assert_eq!(loaded.to_token_opt("<s>"), None);
+    // new words continue after the loaded ones.
+    assert_eq!(loaded.to_token("mat"), vocab.to_token("mat"));
+}
+
+#[test]
+fn case_insensitive_vocab_keeps_the_first_casing() {
+    let mut vocab = Vocab::new_case_insensitive();
+    let hello = vocab.to_token("Hello");
+    assert_eq!(vocab.to_token("hello"), hello);
+    assert_eq!(vocab.to_token("HELLO"), hello);
+    assert_eq!(vocab.to_token_opt("hElLo"), Some(hello));
+    assert_eq!(vocab.to_word(hello), "Hello");
+    assert_eq!(vocab.len(), 1);
+
+    let mut exact = Vocab::new();
+    assert_ne!(exact.to_token("Hello"), exact.to_token("hello"));
+}
+
+#[cfg(feature = "serde")]
+#[test]
+fn vocab_serializes_its_normalization() {
+    let mut vocab = Vocab::new_case_insensitive();
+    let hello = vocab.to_token("Hello");
+    let json = serde_json::to_string(&vocab).unwrap();
+    assert!(!json.contains("fold_case"));
+    let loaded: Vocab = serde_json::from_str(&json).unwrap();
+    assert_eq!(loaded.normalization(), vocab.normalization());
+    assert_eq!(loaded.to_token_opt("hello"), Some(hello));
+}